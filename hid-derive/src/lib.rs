@@ -0,0 +1,331 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, LitInt, Member, Token, Type};
+#[derive(Default)]
+struct FieldAttributes {
+  usage: Option<u32>,
+  usage_minimum: Option<u32>,
+  usage_maximum: Option<u32>,
+  logical_minimum: Option<i32>,
+  logical_maximum: Option<i32>,
+  physical_minimum: Option<i32>,
+  physical_maximum: Option<i32>,
+  unit_exponent: Option<i8>,
+  unit: Option<u32>,
+  designator_index: Option<u32>,
+  string_index: Option<u32>,
+  size: Option<u32>,
+  array: bool,
+  constant: bool,
+  relative: bool,
+  wrap: bool,
+  nonlinear: bool,
+  no_preferred: bool,
+  null_state: bool,
+  volatile: bool,
+  buffered_bytes: bool,
+}
+fn parse_int<T>(meta: &ParseNestedMeta) -> syn::Result<T> where T: FromStr, T::Err: Display {
+  let value = meta.value()?;
+  let negative = value.peek(Token![-]);
+  if negative {
+    value.parse::<Token![-]>()?;
+  };
+  let literal: LitInt = value.parse()?;
+  let digits = if negative { format!("-{}", literal.base10_digits()) } else { literal.base10_digits().to_string() };
+  digits.parse::<T>().map_err(|error| syn::Error::new(literal.span(), error))
+}
+fn parse_field_attributes(field: &syn::Field) -> syn::Result<FieldAttributes> {
+  let mut attributes = FieldAttributes::default();
+  for attribute in &field.attrs {
+    if !attribute.path().is_ident("hid") {
+      continue;
+    };
+    attribute.parse_nested_meta(|meta| {
+      let Some(name) = meta.path.get_ident().map(|name| name.to_string()) else {
+        return Err(meta.error("Expected an identifier"));
+      };
+      match name.as_str() {
+        "usage" => attributes.usage = Some(parse_int(&meta)?),
+        "usage_min" => attributes.usage_minimum = Some(parse_int(&meta)?),
+        "usage_max" => attributes.usage_maximum = Some(parse_int(&meta)?),
+        "logical_min" => attributes.logical_minimum = Some(parse_int(&meta)?),
+        "logical_max" => attributes.logical_maximum = Some(parse_int(&meta)?),
+        "physical_min" => attributes.physical_minimum = Some(parse_int(&meta)?),
+        "physical_max" => attributes.physical_maximum = Some(parse_int(&meta)?),
+        "unit_exponent" => attributes.unit_exponent = Some(parse_int(&meta)?),
+        "unit" => attributes.unit = Some(parse_int(&meta)?),
+        "designator_index" => attributes.designator_index = Some(parse_int(&meta)?),
+        "string_index" => attributes.string_index = Some(parse_int(&meta)?),
+        "size" => attributes.size = Some(parse_int(&meta)?),
+        "array" => attributes.array = true,
+        "constant" => attributes.constant = true,
+        "relative" => attributes.relative = true,
+        "wrap" => attributes.wrap = true,
+        "nonlinear" => attributes.nonlinear = true,
+        "no_preferred" => attributes.no_preferred = true,
+        "null" => attributes.null_state = true,
+        "volatile" => attributes.volatile = true,
+        "buffered_bytes" => attributes.buffered_bytes = true,
+        _ => return Err(meta.error(format!("Unknown hid field attribute `{name}`"))),
+      };
+      Ok(())
+    })?;
+  };
+  if attributes.usage.is_some() && (attributes.usage_minimum.is_some() || attributes.usage_maximum.is_some()) {
+    return Err(syn::Error::new_spanned(field, "`usage` cannot be combined with `usage_min` or `usage_max`"));
+  };
+  if attributes.usage_minimum.is_some() != attributes.usage_maximum.is_some() {
+    return Err(syn::Error::new_spanned(field, "`usage_min` and `usage_max` must be used together"));
+  };
+  if let Some(exponent) = attributes.unit_exponent && !(-8..=7).contains(&exponent) {
+    return Err(syn::Error::new_spanned(field, "`unit_exponent` must be between -8 and 7"));
+  };
+  if attributes.array && attributes.usage.is_none() && attributes.usage_minimum.is_none() {
+    return Err(syn::Error::new_spanned(field, "`array` fields require `usage` or `usage_min` and `usage_max`"));
+  };
+  if let (Some(minimum), Some(maximum)) = (attributes.usage_minimum, attributes.usage_maximum) && minimum > maximum {
+    return Err(syn::Error::new_spanned(field, "`usage_min` must not be greater than `usage_max`"));
+  };
+  Ok(attributes)
+}
+fn optional<T: ToTokens>(value: Option<T>) -> TokenStream2 {
+  match value {
+    Some(value) => quote! { ::std::option::Option::Some(#value) },
+    None => quote! { ::std::option::Option::None },
+  }
+}
+fn is_option(ty: &Type) -> bool {
+  match ty {
+    Type::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+    _ => false,
+  }
+}
+fn report_field(attributes: &FieldAttributes, element: &Type, count: &TokenStream2, input: bool) -> TokenStream2 {
+  let size = match attributes.size {
+    Some(size) => quote! { #size },
+    None => quote! { <#element as ::hid::reports::ReportValue>::BITS },
+  };
+  let constant = attributes.constant;
+  let relative = attributes.relative;
+  let designator_index = optional(attributes.designator_index);
+  let string_index = optional(attributes.string_index);
+  if attributes.array {
+    let usage_range = match (attributes.usage, attributes.usage_minimum, attributes.usage_maximum) {
+      (Some(usage), _, _) => quote! { ::std::vec![#usage] },
+      (None, Some(minimum), Some(maximum)) => quote! { (#minimum..=#maximum).collect::<::std::vec::Vec<u32>>() },
+      _ => unreachable!(),
+    };
+    let logical_minimum = match attributes.logical_minimum {
+      Some(minimum) => quote! { #minimum },
+      None => quote! { 0 },
+    };
+    let logical_maximum = match attributes.logical_maximum {
+      Some(maximum) => quote! { #maximum },
+      None => quote! { logical_minimum+(usage_range.len() as i32)-1 },
+    };
+    return quote! {
+      for _ in 0..(#count) {
+        let usage_range = #usage_range;
+        let logical_minimum: i32 = #logical_minimum;
+        fields.push(::hid::descriptors::ReportField::Array {
+          size: #size,
+          logical_minimum,
+          logical_maximum: #logical_maximum,
+          usage_range,
+          designator_range: #designator_index.into_iter().collect(),
+          string_range: #string_index.into_iter().collect(),
+          constant: #constant,
+          relative: #relative,
+        });
+      };
+    };
+  };
+  let (index, usages) = match (attributes.usage, attributes.usage_minimum, attributes.usage_maximum) {
+    (Some(usage), _, _) => (quote! { _ }, quote! { ::std::option::Option::Some(::std::vec![#usage]) }),
+    (None, Some(minimum), Some(maximum)) => (quote! { index }, quote! { ::std::option::Option::Some(::std::vec![(#minimum+index as u32).min(#maximum)]) }),
+    _ => (quote! { _ }, quote! { ::std::option::Option::None }),
+  };
+  let logical_minimum = match attributes.logical_minimum {
+    Some(minimum) => quote! { #minimum },
+    None => quote! { logical_range.0 },
+  };
+  let logical_maximum = match attributes.logical_maximum {
+    Some(maximum) => quote! { #maximum },
+    None => quote! { logical_range.1 },
+  };
+  let physical_minimum = optional(attributes.physical_minimum);
+  let physical_maximum = optional(attributes.physical_maximum);
+  let unit_exponent = optional(attributes.unit_exponent);
+  let unit = optional(attributes.unit.map(|unit| quote! { ::hid::units::Unit::from_raw(#unit) }));
+  let wrap = attributes.wrap;
+  let linear = !attributes.nonlinear;
+  let preferred_state = !attributes.no_preferred;
+  let null_state = attributes.null_state || is_option(element);
+  let volatile = if input { quote! { ::std::option::Option::None } } else { optional(Some(attributes.volatile)) };
+  let buffered_bytes = attributes.buffered_bytes;
+  quote! {
+    for #index in 0..(#count) {
+      let size: u32 = #size;
+      let logical_range = <#element as ::hid::reports::ReportValue>::logical_range(size);
+      fields.push(::hid::descriptors::ReportField::Variable {
+        size,
+        logical_minimum: #logical_minimum,
+        logical_maximum: #logical_maximum,
+        physical_minimum: #physical_minimum,
+        physical_maximum: #physical_maximum,
+        unit_exponent: #unit_exponent,
+        unit: #unit,
+        usages: #usages,
+        designator_index: #designator_index,
+        string_index: #string_index,
+        constant: #constant,
+        relative: #relative,
+        wrap: #wrap,
+        linear: #linear,
+        preferred_state: #preferred_state,
+        null_state: #null_state,
+        volatile: #volatile,
+        buffered_bytes: #buffered_bytes,
+      });
+    };
+  }
+}
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+  let Data::Struct(data) = &input.data else {
+    return Err(syn::Error::new_spanned(&input, "HidReport can only be derived for structs"));
+  };
+  let mut ty = quote! { ::hid::descriptors::ReportType::Input };
+  let mut input_report = true;
+  let mut id: Option<u8> = None;
+  for attribute in &input.attrs {
+    if !attribute.path().is_ident("hid") {
+      continue;
+    };
+    attribute.parse_nested_meta(|meta| {
+      if meta.path.is_ident("input") {
+        ty = quote! { ::hid::descriptors::ReportType::Input };
+        input_report = true;
+      }
+      else if meta.path.is_ident("output") {
+        ty = quote! { ::hid::descriptors::ReportType::Output };
+        input_report = false;
+      }
+      else if meta.path.is_ident("feature") {
+        ty = quote! { ::hid::descriptors::ReportType::Feature };
+        input_report = false;
+      }
+      else if meta.path.is_ident("id") {
+        let value: u8 = parse_int(&meta)?;
+        if value == 0 {
+          return Err(meta.error("Report ID must not be zero"));
+        };
+        id = Some(value);
+      }
+      else {
+        return Err(meta.error("Unknown hid report attribute"));
+      };
+      Ok(())
+    })?;
+  };
+  let fields: Vec<&syn::Field> = match &data.fields {
+    Fields::Named(fields) => fields.named.iter().collect(),
+    Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+    Fields::Unit => vec![],
+  };
+  let mut definitions = vec![];
+  let mut values = vec![];
+  let mut initializers = vec![];
+  let mut counts = vec![];
+  for (position, field) in fields.into_iter().enumerate() {
+    let attributes = parse_field_attributes(field)?;
+    let member = match &field.ident {
+      Some(ident) => Member::Named(ident.clone()),
+      None => Member::Unnamed(position.into()),
+    };
+    let (element, count): (&Type, Option<&Expr>) = match &field.ty {
+      Type::Array(array) => (&array.elem, Some(&array.len)),
+      ty => (ty, None),
+    };
+    let count_tokens = match count {
+      Some(count) => quote! { #count },
+      None => quote! { 1 },
+    };
+    definitions.push(report_field(&attributes, element, &count_tokens, input_report));
+    counts.push(count_tokens);
+    match count {
+      Some(count) => {
+        values.push(quote! {
+          for element in &self.#member {
+            values.push(::hid::reports::ReportFieldValue::from_value(&report.fields[values.len()], ::std::clone::Clone::clone(element)));
+          };
+        });
+        initializers.push(quote! {
+          #member: {
+            let mut elements: ::std::vec::Vec<#element> = ::std::vec::Vec::with_capacity(#count);
+            for _ in 0..(#count) {
+              let (index, value) = values.next().unwrap();
+              elements.push(value.to_value(index)?);
+            };
+            match elements.try_into() {
+              ::std::result::Result::Ok(elements) => elements,
+              ::std::result::Result::Err(_) => unreachable!(),
+            }
+          }
+        });
+      },
+      None => {
+        values.push(quote! {
+          values.push(::hid::reports::ReportFieldValue::from_value(&report.fields[values.len()], ::std::clone::Clone::clone(&self.#member)));
+        });
+        initializers.push(quote! {
+          #member: {
+            let (index, value) = values.next().unwrap();
+            value.to_value(index)?
+          }
+        });
+      },
+    };
+  };
+  let name = &input.ident;
+  let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+  let id = optional(id);
+  Ok(quote! {
+    impl #impl_generics ::hid::reports::HidReport for #name #type_generics #where_clause {
+      fn report() -> ::hid::descriptors::Report {
+        let mut fields = ::std::vec::Vec::new();
+        #(#definitions)*
+        ::hid::descriptors::Report {
+          ty: #ty,
+          id: #id,
+          fields,
+        }
+      }
+      fn values(&self, report: &::hid::descriptors::Report) -> ::std::vec::Vec<::hid::reports::ReportFieldValue> {
+        let mut values = ::std::vec::Vec::with_capacity(report.fields.len());
+        #(#values)*
+        values
+      }
+      #[allow(unused_mut)]
+      fn try_from_values(values: &[::hid::reports::ReportFieldValue]) -> ::hid::error::Result<Self> {
+        let expected: usize = 0 #(+(#counts))*;
+        if values.len() != expected {
+          return ::std::result::Result::Err(::hid::error::Error::ValueCountMismatch { expected, found: values.len() });
+        };
+        let mut values = values.iter().enumerate();
+        ::std::result::Result::Ok(Self {
+          #(#initializers),*
+        })
+      }
+    }
+  })
+}
+#[proc_macro_derive(HidReport, attributes(hid))]
+pub fn derive_hid_report(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  expand(input).unwrap_or_else(|error| error.to_compile_error()).into()
+}
@@ -1,4 +1,7 @@
+#![allow(clippy::unusual_byte_groupings)]
 use bitvec::prelude::*;
+use crate::error::{Error, Result};
+use crate::units::{normalize_unit_exponent, Unit};
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ReportConstantFlag {
   Data,
@@ -66,6 +69,23 @@ pub enum DelimiterFlag {
   Open,
 }
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ItemType {
+  Main,
+  Global,
+  Local,
+  Reserved,
+}
+impl ItemType {
+  pub(crate) fn is_reserved_tag(self, tag: u8, length: usize) -> bool {
+    match self {
+      ItemType::Main => tag <= 0xF && !(0x8..=0xC).contains(&tag),
+      ItemType::Global => (0xC..=0xF).contains(&tag),
+      ItemType::Local => tag == 0x6 || (0xB..=0xF).contains(&tag),
+      ItemType::Reserved => tag < 0xF || (tag == 0xF && length != 2),
+    }
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum DescriptorItem {
   Input { constant: ReportConstantFlag, layout: ReportLayoutFlag, relative: ReportRelativeFlag, wrap: ReportWrapFlag, linear: ReportLinearFlag, preferred_state: ReportPreferredStateFlag, null_state: ReportNullStateFlag, buffered_bytes: ReportBufferedBytesFlag },
   Output { constant: ReportConstantFlag, layout: ReportLayoutFlag, relative: ReportRelativeFlag, wrap: ReportWrapFlag, linear: ReportLinearFlag, preferred_state: ReportPreferredStateFlag, null_state: ReportNullStateFlag, volatile: ReportVolatileFlag, buffered_bytes: ReportBufferedBytesFlag },
@@ -78,7 +98,7 @@ pub enum DescriptorItem {
   PhysicalMinimum(i32),
   PhysicalMaximum(i32),
   UnitExponent(i8),
-  Unit(u32),
+  Unit(Unit),
   ReportSize(u32),
   ReportId(u8),
   ReportCount(u32),
@@ -94,12 +114,56 @@ pub enum DescriptorItem {
   StringMinimum(u32),
   StringMaximum(u32),
   Delimiter(DelimiterFlag),
+  Long { tag: u8, data: Vec<u8> },
+  Reserved { kind: ItemType, tag: u8, data: Vec<u8> },
 }
 impl DescriptorItem {
   pub fn into_bitvec(self) -> BitVec<u8, Msb0> {
-    let mut data = bitvec![u8, Msb0; 0; 40];
-  let mut needs_extra_byte = false;
-    match self {
+    self.try_into_bitvec().unwrap_or_else(|error| panic!("{error}"))
+  }
+  pub fn try_into_bitvec(self) -> Result<BitVec<u8, Msb0>> {
+    let mut bytes = vec![];
+    self.try_write_into(&mut bytes)?;
+    Ok(BitVec::from_vec(bytes))
+  }
+  pub fn write_into(&self, output: &mut Vec<u8>) {
+    self.try_write_into(output).unwrap_or_else(|error| panic!("{error}"));
+  }
+  pub fn try_write_into(&self, output: &mut Vec<u8>) -> Result<()> {
+    self.write_sized_into(output, 0, UnitExponentForm::Byte)
+  }
+  pub fn write_into_with(&self, output: &mut Vec<u8>, form: UnitExponentForm) {
+    self.try_write_into_with(output, form).unwrap_or_else(|error| panic!("{error}"));
+  }
+  pub fn try_write_into_with(&self, output: &mut Vec<u8>, form: UnitExponentForm) -> Result<()> {
+    self.write_sized_into(output, 0, form)
+  }
+  fn write_sized_into(&self, output: &mut Vec<u8>, minimum_size: u8, form: UnitExponentForm) -> Result<()> {
+    let (header, length) = self.encode_header(minimum_size, form)?;
+    output.extend_from_slice(&header[..length]);
+    if let DescriptorItem::Long { data, .. } = self {
+      output.extend_from_slice(data);
+    };
+    Ok(())
+  }
+  pub fn encode_into(&self, output: &mut [u8]) -> Result<usize> {
+    let (header, header_length) = self.encode_header(0, UnitExponentForm::Byte)?;
+    let data: &[u8] = match self {
+      DescriptorItem::Long { data, .. } => data,
+      _ => &[],
+    };
+    let length = header_length+data.len();
+    if output.len() < length {
+      return Err(Error::BufferTooSmall { expected: length, found: output.len() });
+    };
+    output[..header_length].copy_from_slice(&header[..header_length]);
+    output[header_length..length].copy_from_slice(data);
+    Ok(length)
+  }
+  fn encode_header(&self, minimum_size: u8, form: UnitExponentForm) -> Result<([u8; 5], usize)> {
+    let mut data = bitarr![u8, Msb0; 0; 40];
+    let mut size = None;
+    match *self {
       DescriptorItem::Input { constant, layout, relative, wrap, linear, preferred_state, null_state, buffered_bytes } => {
         data[0..6].store::<u8>(0b1000_00);
         data.set(15, constant==ReportConstantFlag::Constant);
@@ -157,67 +221,38 @@ impl DescriptorItem {
       },
       DescriptorItem::LogicalMinimum(minimum) => {
         data[0..6].store::<u8>(0b0001_01);
-        match minimum {
-          minimum if minimum>=(i8::MIN as i32) && minimum<=(i8::MAX as i32) => data[8..16].store::<i8>(minimum as i8),
-          minimum if minimum>=(i16::MIN as i32) && minimum<=(i16::MAX as i32) => {
-            needs_extra_byte = minimum > 0 && minimum <= u8::MAX as i32;
-            data[8..24].store::<i16>(minimum as i16)
-          },
-          minimum=> {
-            needs_extra_byte = minimum > 0 && minimum <= u16::MAX as i32;
-            data[8..40].store::<i32>(minimum)
-          },
-        };
+        data[8..40].store::<i32>(minimum);
+        size = Some(signed_size(minimum));
       },
       DescriptorItem::LogicalMaximum(maximum) => {
         data[0..6].store::<u8>(0b0010_01);
-        match maximum {
-          maximum if maximum>=(i8::MIN as i32) && maximum<=(i8::MAX as i32) => data[8..16].store::<i8>(maximum as i8),
-          maximum if maximum>=(i16::MIN as i32) && maximum<=(i16::MAX as i32) => {
-            needs_extra_byte = maximum > 0 && maximum <= u8::MAX as i32;
-            data[8..24].store::<i16>(maximum as i16)
-          },
-          maximum=> {
-            needs_extra_byte = maximum > 0 && maximum <= u16::MAX as i32;
-            data[8..40].store::<i32>(maximum)
-          },
-        };
+        data[8..40].store::<i32>(maximum);
+        size = Some(signed_size(maximum));
       },
       DescriptorItem::PhysicalMinimum(minimum) => {
         data[0..6].store::<u8>(0b0011_01);
-        match minimum {
-          minimum if minimum>=(i8::MIN as i32) && minimum<=(i8::MAX as i32) => data[8..16].store::<i8>(minimum as i8),
-          minimum if minimum>=(i16::MIN as i32) && minimum<=(i16::MAX as i32) => {
-            needs_extra_byte = minimum > 0 && minimum <= u8::MAX as i32;
-            data[8..24].store::<i16>(minimum as i16)
-          },
-          minimum=> {
-            needs_extra_byte = minimum > 0 && minimum <= u16::MAX as i32;
-            data[8..40].store::<i32>(minimum)
-          },
-        };
+        data[8..40].store::<i32>(minimum);
+        size = Some(signed_size(minimum));
       },
       DescriptorItem::PhysicalMaximum(maximum) => {
         data[0..6].store::<u8>(0b0100_01);
-        match maximum {
-          maximum if maximum>=(i8::MIN as i32) && maximum<=(i8::MAX as i32) => data[8..16].store::<i8>(maximum as i8),
-          maximum if maximum>=(i16::MIN as i32) && maximum<=(i16::MAX as i32) => {
-            needs_extra_byte = maximum > 0 && maximum <= u8::MAX as i32;
-            data[8..24].store::<i16>(maximum as i16)
-          },
-          maximum=> {
-            needs_extra_byte = maximum > 0 && maximum <= u16::MAX as i32;
-            data[8..40].store::<i32>(maximum)
-          },
-        };
+        data[8..40].store::<i32>(maximum);
+        size = Some(signed_size(maximum));
+      },
+      DescriptorItem::UnitExponent(exponent) if !(-8..=7).contains(&exponent) => return Err(Error::UnitExponentOutOfRange { exponent }),
+      DescriptorItem::UnitExponent(exponent) if form == UnitExponentForm::Nibble => {
+        data[0..6].store::<u8>(0b0101_01);
+        data[8..16].store::<u8>(exponent as u8 & 0xF);
+        size = Some(if exponent == 0 { 0 } else { 1 });
       },
       DescriptorItem::UnitExponent(exponent) => {
         data[0..6].store::<u8>(0b0101_01);
-        data[8..16].store::<i8>(exponent);
+        data[8..40].store::<i32>(exponent as i32);
+        size = Some(signed_size(exponent as i32));
       },
       DescriptorItem::Unit(unit) => {
         data[0..6].store::<u8>(0b0110_01);
-        data[8..40].store::<u32>(unit);
+        data[8..40].store::<u32>(unit.raw());
       },
       DescriptorItem::ReportSize(size) => {
         data[0..6].store::<u8>(0b0111_01);
@@ -241,21 +276,30 @@ impl DescriptorItem {
         data[0..6].store::<u8>(0b0000_10);
         match usage {
           UsageSpecifier::Usage(usage) => data[8..24].store::<u16>(usage),
-          UsageSpecifier::ExtendedUsage(usage) => data[8..40].store::<u32>(usage),
+          UsageSpecifier::ExtendedUsage(usage) => {
+            data[8..40].store::<u32>(usage);
+            size = Some(4);
+          },
         };
       },
       DescriptorItem::UsageMinimum(usage) => {
         data[0..6].store::<u8>(0b0001_10);
         match usage {
           UsageSpecifier::Usage(usage) => data[8..24].store::<u16>(usage),
-          UsageSpecifier::ExtendedUsage(usage) => data[8..40].store::<u32>(usage),
+          UsageSpecifier::ExtendedUsage(usage) => {
+            data[8..40].store::<u32>(usage);
+            size = Some(4);
+          },
         };
       },
       DescriptorItem::UsageMaximum(usage) => {
         data[0..6].store::<u8>(0b0010_10);
         match usage {
           UsageSpecifier::Usage(usage) => data[8..24].store::<u16>(usage),
-          UsageSpecifier::ExtendedUsage(usage) => data[8..40].store::<u32>(usage),
+          UsageSpecifier::ExtendedUsage(usage) => {
+            data[8..40].store::<u32>(usage);
+            size = Some(4);
+          },
         };
       },
       DescriptorItem::DesignatorIndex(index) => {
@@ -286,46 +330,101 @@ impl DescriptorItem {
         data[0..6].store::<u8>(0b1010_10);
         data.set(15, flag==DelimiterFlag::Open);
       },
+      DescriptorItem::Long { tag, ref data } => {
+        let length = u8::try_from(data.len()).map_err(|_| Error::UnencodableItemData { length: data.len() })?;
+        return Ok(([0b1111_11_10, length, tag, 0, 0], 3));
+      },
+      DescriptorItem::Reserved { kind, tag, ref data } => {
+        let size: u8 = match data.len() {
+          0 => 0,
+          1 => 1,
+          2 => 2,
+          4 => 3,
+          length => return Err(Error::UnencodableItemData { length }),
+        };
+        if !kind.is_reserved_tag(tag, data.len()) {
+          return Err(Error::InvalidReservedTag { kind, tag });
+        };
+        let kind: u8 = match kind {
+          ItemType::Main => 0,
+          ItemType::Global => 1,
+          ItemType::Local => 2,
+          ItemType::Reserved => 3,
+        };
+        let mut header = [(tag << 4) | (kind << 2) | size, 0, 0, 0, 0];
+        header[1..1+data.len()].copy_from_slice(data);
+        return Ok((header, 1+data.len()));
+      },
     };
-    let mut has_value = vec![false; 4];
+    let mut has_value = [false; 4];
     for i in 0..4 {
       has_value[i] = data[8+i*8..16+i*8].load::<u8>()!=0;
     };
-    let size: u8 = match has_value[0..4] {
+    let size = size.unwrap_or(match has_value[0..4] {
       [false, false, false, false] => 0,
       [true, false, false, false] => 1,
       [_, true, false, false] => 2,
       _ => 4,
+    });
+    let size = match size.max(minimum_size) {
+      3 => 4,
+      size => size,
     };
-  let size = match (size, needs_extra_byte) {
-    (0, true) => 1,
-    (1, true) => 2,
-    (2, true) => 4,
-    (size, _) => size,
-  };
-    data.truncate((8+size*8) as usize);
     data[6..8].store::<u8>(match size {
       4 => 3,
       size => size,
     });
-    data
+    Ok((data.into_inner(), 1+size as usize))
   }
   pub fn from_bitvec(item: BitVec<u8, Msb0>) -> Self {
-    if item.len() < 8 || item.len()%8 != 0 {
-      panic!("HID descriptor items must be at least one byte long and their size must be a multiple of bytes");
+    DescriptorItem::try_from_bitvec(item).unwrap_or_else(|error| panic!("{error}"))
+  }
+  pub fn try_from_bitvec(item: BitVec<u8, Msb0>) -> Result<Self> {
+    DescriptorItem::from_bits_at(&item, 0)
+  }
+  pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+    DescriptorItem::parse_at(data, 0)
+  }
+  fn parse_at(data: &[u8], offset: usize) -> Result<(Self, usize)> {
+    let length = match data {
+      [] | [0b1111_11_10] => return Err(Error::UnexpectedEnd { offset }),
+      [0b1111_11_10, size, ..] => 3+*size as usize,
+      [prefix, ..] => 1+match prefix & 0b11 {
+        3 => 4,
+        size => size as usize,
+      },
+    };
+    if data.len() < length {
+      return Err(Error::UnexpectedEnd { offset });
+    };
+    Ok((DescriptorItem::from_bits_at(data[..length].view_bits(), offset)?, length))
+  }
+  fn from_bits_at(item: &BitSlice<u8, Msb0>, offset: usize) -> Result<Self> {
+    if item.len() < 8 || !item.len().is_multiple_of(8) {
+      return Err(Error::InvalidItemLength { offset });
+    };
+    let tag = item[0..8].load::<u8>();
+    if tag == 0b1111_11_10 {
+      if item.len() < 24 || item.len() != 24+(item[8..16].load::<u8>() as usize)*8 {
+        return Err(Error::InvalidItemLength { offset });
+      };
+      return Ok(DescriptorItem::Long {
+        tag: item[16..24].load::<u8>(),
+        data: item[24..].to_bitvec().into_vec(),
+      });
     };
     let size = match item[6..8].load::<u8>() {
       3 => 4,
       size => size,
     };
     if item.len() != (8+size*8) as usize {
-      panic!("The size field does not match the length of the data");
+      return Err(Error::InvalidItemLength { offset });
     };
-    let mut data = bitvec![u8, Msb0; 0; 32];
+    let mut data = bitarr![u8, Msb0; 0; 32];
     for i in 0..(size as usize) {
       data[8*i..8+8*i].store::<u8>(item[8+8*i..16+8*i].load::<u8>());
     };
-    match item[0..6].load::<u8>() {
+    Ok(match item[0..6].load::<u8>() {
       0b1000_00 => DescriptorItem::Input {
         constant: if *data.get(7).unwrap() { ReportConstantFlag::Constant } else { ReportConstantFlag::Data },
         layout: if *data.get(6).unwrap() { ReportLayoutFlag::Variable } else { ReportLayoutFlag::Array },
@@ -366,8 +465,8 @@ impl DescriptorItem {
         4 => CollectionType::NamedArray,
         5 => CollectionType::UsageSwitch,
         6 => CollectionType::UsageModifier,
-        n if n >= 128 && n <= 255 => CollectionType::VendorDefined(n as u8),
-        _ => panic!("Invalid collection type"),
+        n if (128..=255).contains(&n) => CollectionType::VendorDefined(n as u8),
+        value => return Err(Error::InvalidCollectionType { offset, tag, value }),
       }),
       0b1100_00 => DescriptorItem::EndCollection,
       0b0000_01 => DescriptorItem::UsagePage(data[0..16].load::<u16>()),
@@ -391,23 +490,33 @@ impl DescriptorItem {
         2 => data[0..16].load::<i16>() as i32,
         _ => data.load::<i32>(),
       }),
-      0b0101_01 => DescriptorItem::UnitExponent(data[0..8].load::<i8>()),
-      0b0110_01 => DescriptorItem::Unit(data.load::<u32>()),
+      0b0101_01 => {
+        let value = match size {
+          1 => data[0..8].load::<i8>() as i32,
+          2 => data[0..16].load::<i16>() as i32,
+          _ => data.load::<i32>(),
+        };
+        match value {
+          -8..=15 => DescriptorItem::UnitExponent(normalize_unit_exponent(value as i8)),
+          _ => return Err(Error::InvalidUnitExponent { offset, value }),
+        }
+      },
+      0b0110_01 => DescriptorItem::Unit(Unit::from_raw(data.load::<u32>())),
       0b0111_01 => DescriptorItem::ReportSize(data.load::<u32>()),
       0b1000_01 => DescriptorItem::ReportId(data[0..8].load::<u8>()),
       0b1001_01 => DescriptorItem::ReportCount(data.load::<u32>()),
       0b1010_01 => DescriptorItem::Push,
       0b1011_01 => DescriptorItem::Pop,
       0b0000_10 => DescriptorItem::Usage(match size {
-        1 | 2 => UsageSpecifier::Usage(data[0..16].load::<u16>()),
+        0..=2 => UsageSpecifier::Usage(data[0..16].load::<u16>()),
         _ => UsageSpecifier::ExtendedUsage(data.load::<u32>()),
       }),
       0b0001_10 => DescriptorItem::UsageMinimum(match size {
-        1 | 2 => UsageSpecifier::Usage(data[0..16].load::<u16>()),
+        0..=2 => UsageSpecifier::Usage(data[0..16].load::<u16>()),
         _ => UsageSpecifier::ExtendedUsage(data.load::<u32>()),
       }),
       0b0010_10 => DescriptorItem::UsageMaximum(match size {
-        1 | 2 => UsageSpecifier::Usage(data[0..16].load::<u16>()),
+        0..=2 => UsageSpecifier::Usage(data[0..16].load::<u16>()),
         _ => UsageSpecifier::ExtendedUsage(data.load::<u32>()),
       }),
       0b0011_10 => DescriptorItem::DesignatorIndex(data.load::<u32>()),
@@ -417,28 +526,261 @@ impl DescriptorItem {
       0b1000_10 => DescriptorItem::StringMinimum(data.load::<u32>()),
       0b1001_10 => DescriptorItem::StringMaximum(data.load::<u32>()),
       0b1010_10 => DescriptorItem::Delimiter(if *data.get(7).unwrap() { DelimiterFlag::Open } else { DelimiterFlag::Close }),
-      _ => panic!("Invalid descriptor item"),
+      _ => DescriptorItem::Reserved {
+        kind: match item[4..6].load::<u8>() {
+          0 => ItemType::Main,
+          1 => ItemType::Global,
+          2 => ItemType::Local,
+          _ => ItemType::Reserved,
+        },
+        tag: item[0..4].load::<u8>(),
+        data: item[8..].to_bitvec().into_vec(),
+      },
+    })
+  }
+}
+fn signed_size(value: i32) -> u8 {
+  match value {
+    0 => 0,
+    value if i8::try_from(value).is_ok() => 1,
+    value if i16::try_from(value).is_ok() => 2,
+    _ => 4,
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnitExponentForm {
+  Byte,
+  Nibble,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ItemEncoding {
+  Minimal,
+  Original,
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EncodedItem {
+  pub item: DescriptorItem,
+  pub original: Option<Vec<u8>>,
+}
+impl From<DescriptorItem> for EncodedItem {
+  fn from(item: DescriptorItem) -> Self {
+    EncodedItem { item, original: None }
+  }
+}
+impl EncodedItem {
+  pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+    let (item, length) = DescriptorItem::parse(data)?;
+    Ok((EncodedItem { item, original: Some(data[..length].to_vec()) }, length))
+  }
+  pub fn write_into(&self, output: &mut Vec<u8>, encoding: ItemEncoding) {
+    self.try_write_into(output, encoding).unwrap_or_else(|error| panic!("{error}"));
+  }
+  pub fn try_write_into(&self, output: &mut Vec<u8>, encoding: ItemEncoding) -> Result<()> {
+    match (encoding, &self.original) {
+      (ItemEncoding::Original, Some(original)) => {
+        if DescriptorItem::parse(original).is_ok_and(|(item, _)| item == self.item) {
+          output.extend_from_slice(original);
+          Ok(())
+        }
+        else {
+          let minimum_size = match (original.first(), &self.item) {
+            (Some(0b1111_11_10) | None, _) => 0,
+            (Some(prefix), DescriptorItem::Usage(UsageSpecifier::Usage(_)) | DescriptorItem::UsageMinimum(UsageSpecifier::Usage(_)) | DescriptorItem::UsageMaximum(UsageSpecifier::Usage(_))) => (prefix & 0b11).min(2),
+            (Some(prefix), _) => prefix & 0b11,
+          };
+          let mut data = vec![];
+          self.item.write_sized_into(&mut data, minimum_size, UnitExponentForm::Byte)?;
+          if DescriptorItem::parse(&data).is_ok_and(|(item, _)| item == self.item) {
+            output.extend_from_slice(&data);
+            Ok(())
+          }
+          else {
+            self.item.try_write_into(output)
+          }
+        }
+      },
+      _ => self.item.try_write_into(output),
     }
   }
 }
+pub struct DescriptorItems<'a> {
+  data: &'a [u8],
+  offset: usize,
+}
+impl Iterator for DescriptorItems<'_> {
+  type Item = Result<DescriptorItem>;
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.data.is_empty() {
+      return None;
+    };
+    match DescriptorItem::parse_at(self.data, self.offset) {
+      Ok((item, length)) => {
+        self.data = &self.data[length..];
+        self.offset += length;
+        Some(Ok(item))
+      },
+      Err(error) => {
+        self.data = &[];
+        Some(Err(error))
+      },
+    }
+  }
+}
+pub fn items_from_bytes(data: &[u8]) -> DescriptorItems<'_> {
+  DescriptorItems { data, offset: 0 }
+}
+pub struct EncodedItems<'a> {
+  items: DescriptorItems<'a>,
+}
+impl Iterator for EncodedItems<'_> {
+  type Item = Result<EncodedItem>;
+  fn next(&mut self) -> Option<Self::Item> {
+    let data = self.items.data;
+    let item = self.items.next()?;
+    let length = data.len()-self.items.data.len();
+    Some(item.map(|item| EncodedItem { item, original: Some(data[..length].to_vec()) }))
+  }
+}
+pub fn encoded_items_from_bytes(data: &[u8]) -> EncodedItems<'_> {
+  EncodedItems { items: items_from_bytes(data) }
+}
+pub fn encoded_items_into_bytes(items: &[EncodedItem], encoding: ItemEncoding) -> Vec<u8> {
+  try_encoded_items_into_bytes(items, encoding).unwrap_or_else(|error| panic!("{error}"))
+}
+pub fn try_encoded_items_into_bytes(items: &[EncodedItem], encoding: ItemEncoding) -> Result<Vec<u8>> {
+  let mut data = vec![];
+  for item in items {
+    item.try_write_into(&mut data, encoding)?;
+  };
+  Ok(data)
+}
+pub fn items_into_bytes(items: &[DescriptorItem]) -> Vec<u8> {
+  items_into_bytes_with(items, UnitExponentForm::Byte)
+}
+pub fn try_items_into_bytes(items: &[DescriptorItem]) -> Result<Vec<u8>> {
+  try_items_into_bytes_with(items, UnitExponentForm::Byte)
+}
+pub fn items_into_bytes_with(items: &[DescriptorItem], form: UnitExponentForm) -> Vec<u8> {
+  try_items_into_bytes_with(items, form).unwrap_or_else(|error| panic!("{error}"))
+}
+pub fn try_items_into_bytes_with(items: &[DescriptorItem], form: UnitExponentForm) -> Result<Vec<u8>> {
+  let mut data = vec![];
+  for item in items {
+    item.try_write_into_with(&mut data, form)?;
+  };
+  Ok(data)
+}
 pub fn items_into_bitvec(items: Vec<DescriptorItem>) -> BitVec<u8, Msb0> {
-  let mut data = BitVec::new();
-  for item in items.into_iter() {
-    data.append(&mut item.into_bitvec());
+  try_items_into_bitvec(items).unwrap_or_else(|error| panic!("{error}"))
+}
+pub fn try_items_into_bitvec(items: Vec<DescriptorItem>) -> Result<BitVec<u8, Msb0>> {
+  Ok(BitVec::from_vec(try_items_into_bytes(&items)?))
+}
+pub fn items_from_bitvec(data: BitVec<u8, Msb0>) -> Vec<DescriptorItem> {
+  try_items_from_bitvec(data).unwrap_or_else(|error| panic!("{error}"))
+}
+pub fn try_items_from_bitvec(data: BitVec<u8, Msb0>) -> Result<Vec<DescriptorItem>> {
+  let length = data.len()/8;
+  let items = items_from_bytes(&data.as_raw_slice()[..length]).collect::<Result<Vec<_>>>()?;
+  if !data.len().is_multiple_of(8) {
+    return Err(Error::UnexpectedEnd { offset: length });
   };
-  data
+  Ok(items)
 }
-pub fn items_from_bitvec(mut data: BitVec<u8, Msb0>) -> Vec<DescriptorItem> {
-  let mut items = vec![];
-  while data.len()>=8 {
-    let size = match data[6..8].load::<u8>() {
-      3 => 4,
-      size => size,
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn round_trip(data: &[u8]) -> Vec<DescriptorItem> {
+    let items = items_from_bytes(data).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(items_into_bytes(&items), data);
+    items
+  }
+  #[test]
+  fn bytes_items_bytes_round_trip() {
+    let items = round_trip(&[0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x03, 0x81, 0x06, 0xC0]);
+    assert_eq!(items[0], DescriptorItem::UsagePage(0x01));
+    assert_eq!(items[3], DescriptorItem::LogicalMinimum(-127));
+    assert_eq!(items[7], DescriptorItem::Input { constant: ReportConstantFlag::Data, layout: ReportLayoutFlag::Variable, relative: ReportRelativeFlag::Relative, wrap: ReportWrapFlag::NoWrap, linear: ReportLinearFlag::Linear, preferred_state: ReportPreferredStateFlag::PreferredState, null_state: ReportNullStateFlag::NoNullPosition, buffered_bytes: ReportBufferedBytesFlag::BitField });
+    assert_eq!(items[8], DescriptorItem::EndCollection);
+  }
+  #[test]
+  fn long_items_round_trip() {
+    let items = round_trip(&[0xFE, 0x03, 0x42, 0x01, 0x02, 0x03, 0xFE, 0x00, 0x43]);
+    assert_eq!(items, [DescriptorItem::Long { tag: 0x42, data: vec![0x01, 0x02, 0x03] }, DescriptorItem::Long { tag: 0x43, data: vec![] }]);
+    let mut data = vec![0xFE, 0xFF, 0x10];
+    data.extend([0xAA; 255]);
+    round_trip(&data);
+  }
+  #[test]
+  fn reserved_items_round_trip() {
+    let items = round_trip(&[0xF5, 0x01, 0xD8, 0xD2, 0x01, 0x02, 0xCF, 0x01, 0x02, 0x03, 0x04]);
+    assert_eq!(items[0], DescriptorItem::Reserved { kind: ItemType::Global, tag: 0x0F, data: vec![0x01] });
+    assert_eq!(items[1], DescriptorItem::Reserved { kind: ItemType::Local, tag: 0x0D, data: vec![] });
+    assert_eq!(items[2], DescriptorItem::Reserved { kind: ItemType::Main, tag: 0x0D, data: vec![0x01, 0x02] });
+    assert_eq!(items[3], DescriptorItem::Reserved { kind: ItemType::Reserved, tag: 0x0C, data: vec![0x01, 0x02, 0x03, 0x04] });
+  }
+  #[test]
+  fn rejects_unencodable_item_data() {
+    let long = DescriptorItem::Long { tag: 0x10, data: vec![0; 256] };
+    assert_eq!(try_items_into_bytes(std::slice::from_ref(&long)), Err(Error::UnencodableItemData { length: 256 }));
+    assert_eq!(long.encode_into(&mut [0; 512]), Err(Error::UnencodableItemData { length: 256 }));
+    let reserved = DescriptorItem::Reserved { kind: ItemType::Global, tag: 0x0F, data: vec![0; 3] };
+    let mut output = vec![];
+    assert_eq!(reserved.try_write_into(&mut output), Err(Error::UnencodableItemData { length: 3 }));
+    assert!(output.is_empty());
+  }
+  #[test]
+  fn rejects_tags_that_are_not_reserved() {
+    let reserved = |kind, tag, data: &[u8]| DescriptorItem::Reserved { kind, tag, data: data.to_vec() };
+    assert_eq!(try_items_into_bytes(&[reserved(ItemType::Global, 0x10, &[])]), Err(Error::InvalidReservedTag { kind: ItemType::Global, tag: 0x10 }));
+    assert_eq!(try_items_into_bytes(&[reserved(ItemType::Main, 0x08, &[0x02])]), Err(Error::InvalidReservedTag { kind: ItemType::Main, tag: 0x08 }));
+    assert_eq!(try_items_into_bytes(&[reserved(ItemType::Local, 0x0A, &[0x01])]), Err(Error::InvalidReservedTag { kind: ItemType::Local, tag: 0x0A }));
+    assert_eq!(try_items_into_bytes(&[reserved(ItemType::Reserved, 0x0F, &[0x01, 0x02])]), Err(Error::InvalidReservedTag { kind: ItemType::Reserved, tag: 0x0F }));
+    let items = [reserved(ItemType::Reserved, 0x0F, &[0x01]), reserved(ItemType::Local, 0x06, &[]), reserved(ItemType::Global, 0x0C, &[0x01, 0x02, 0x03, 0x04])];
+    let bytes = try_items_into_bytes(&items).unwrap();
+    assert_eq!(bytes, [0xFD, 0x01, 0x68, 0xC7, 0x01, 0x02, 0x03, 0x04]);
+    assert_eq!(items_from_bytes(&bytes).collect::<Result<Vec<_>>>(), Ok(items.to_vec()));
+  }
+  #[test]
+  fn original_encoding_keeps_sizes() {
+    let data = [0x0B, 0x30, 0x00, 0x01, 0x00, 0x16, 0x00, 0x00, 0x26, 0xFF, 0x00, 0x0A, 0x30, 0x00];
+    let mut items = encoded_items_from_bytes(&data).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(encoded_items_into_bytes(&items, ItemEncoding::Original), data);
+    assert_eq!(encoded_items_into_bytes(&items, ItemEncoding::Minimal), [0x0B, 0x30, 0x00, 0x01, 0x00, 0x14, 0x26, 0xFF, 0x00, 0x09, 0x30]);
+    items[0].item = DescriptorItem::Usage(UsageSpecifier::Usage(0x31));
+    items[1].item = DescriptorItem::LogicalMinimum(-1);
+    items[2].item = DescriptorItem::LogicalMaximum(0x7F);
+    items[3].item = DescriptorItem::Usage(UsageSpecifier::ExtendedUsage(0x0001_0031));
+    let bytes = encoded_items_into_bytes(&items, ItemEncoding::Original);
+    assert_eq!(bytes, [0x0A, 0x31, 0x00, 0x16, 0xFF, 0xFF, 0x26, 0x7F, 0x00, 0x0B, 0x31, 0x00, 0x01, 0x00]);
+    let parsed = items_from_bytes(&bytes).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(parsed, items.into_iter().map(|item| item.item).collect::<Vec<_>>());
+  }
+  #[test]
+  fn unit_exponents_round_trip() {
+    for exponent in -8..=7 {
+      for form in [UnitExponentForm::Byte, UnitExponentForm::Nibble] {
+        let bytes = items_into_bytes_with(&[DescriptorItem::UnitExponent(exponent)], form);
+        assert_eq!(DescriptorItem::parse(&bytes), Ok((DescriptorItem::UnitExponent(exponent), bytes.len())));
+      };
     };
-    let remainder = data.split_off(8+(size as usize)*8);
-    let item = data;
-    data = remainder;
-    items.push(DescriptorItem::from_bitvec(item));
-  };
-  items
+    assert_eq!(items_into_bytes_with(&[DescriptorItem::UnitExponent(-3)], UnitExponentForm::Nibble), [0x55, 0x0D]);
+    assert_eq!(DescriptorItem::parse(&[0x55, 0x0C]), Ok((DescriptorItem::UnitExponent(-4), 2)));
+    assert_eq!(DescriptorItem::parse(&[0x55, 0xF8]), Ok((DescriptorItem::UnitExponent(-8), 2)));
+    assert_eq!(DescriptorItem::parse(&[0x55, 0x10]), Err(Error::InvalidUnitExponent { offset: 0, value: 16 }));
+    assert_eq!(DescriptorItem::parse(&[0x55, 0xF7]), Err(Error::InvalidUnitExponent { offset: 0, value: -9 }));
+    assert_eq!(DescriptorItem::parse(&[0x56, 0x00, 0x01]), Err(Error::InvalidUnitExponent { offset: 0, value: 256 }));
+    for exponent in [-9, 8, 12, i8::MIN] {
+      assert_eq!(try_items_into_bytes(&[DescriptorItem::UnitExponent(exponent)]), Err(Error::UnitExponentOutOfRange { exponent }));
+    };
+  }
+  #[test]
+  fn rejects_truncated_items() {
+    assert_eq!(DescriptorItem::parse(&[0xFE, 0x03, 0x42, 0x01]), Err(Error::UnexpectedEnd { offset: 0 }));
+    assert_eq!(DescriptorItem::parse(&[0x27, 0x01, 0x02]), Err(Error::UnexpectedEnd { offset: 0 }));
+    let mut items = items_from_bytes(&[0x05, 0x01, 0x09]);
+    assert_eq!(items.next(), Some(Ok(DescriptorItem::UsagePage(0x01))));
+    assert_eq!(items.next(), Some(Err(Error::UnexpectedEnd { offset: 2 })));
+    assert_eq!(items.next(), None);
+  }
 }
@@ -1,5 +1,6 @@
 use crate::descriptor_items::*;
-use hut::{AsUsage, AsUsagePage, Usage};
+use crate::error::{Error, Result};
+use crate::units::Unit;
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ReportType {
   Input,
@@ -15,8 +16,10 @@ pub enum ReportField {
     physical_minimum: Option<i32>,
     physical_maximum: Option<i32>,
     unit_exponent: Option<i8>,
-    unit: Option<u32>,
+    unit: Option<Unit>,
     usages: Option<Vec<u32>>,
+    designator_index: Option<u32>,
+    string_index: Option<u32>,
     constant: bool,
     relative: bool,
     wrap: bool,
@@ -31,11 +34,26 @@ pub enum ReportField {
     logical_minimum: i32,
     logical_maximum: i32,
     usage_range: Vec<u32>,
+    designator_range: Vec<u32>,
+    string_range: Vec<u32>,
     constant: bool,
     relative: bool,
   },
 }
-#[derive(Debug, PartialEq, Eq, Clone)]
+impl ReportField {
+  pub fn size(&self) -> u32 {
+    match self {
+      ReportField::Variable { size, .. } | ReportField::Array { size, .. } => *size,
+    }
+  }
+  pub fn is_bytes(&self) -> bool {
+    match *self {
+      ReportField::Variable { size, buffered_bytes, .. } => buffered_bytes || size > 32,
+      ReportField::Array { .. } => false,
+    }
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
 struct GlobalState {
   usage_page: Option<u16>,
   logical_minimum: Option<i32>,
@@ -43,26 +61,117 @@ struct GlobalState {
   physical_minimum: Option<i32>,
   physical_maximum: Option<i32>,
   unit_exponent: Option<i8>,
-  unit: Option<u32>,
+  unit: Option<Unit>,
   report_size: Option<u32>,
   report_id: Option<u8>,
   report_count: Option<u32>,
+  scope: Option<Box<GlobalState>>,
 }
-impl Default for GlobalState {
-  fn default() -> Self {
-    GlobalState {
-    usage_page: None,
-    logical_minimum: None,
-    logical_maximum: None,
-    physical_minimum: None,
-    physical_maximum: None,
-    unit_exponent: None,
-    unit: None,
-    report_size: None,
-    report_id: None,
-    report_count: None,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct UsageValue(u32);
+impl UsageValue {
+  fn usage_page_value(&self) -> u16 {
+    (self.0 >> 16) as u16
+  }
+  fn usage_id_value(&self) -> u16 {
+    self.0 as u16
+  }
+}
+pub const MAX_REPORT_LENGTH: usize = 16384;
+const MAX_REPORT_BITS: usize = MAX_REPORT_LENGTH*8;
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum LocalUsage {
+  Alternatives(Vec<u32>),
+  Range(u32, u32),
+}
+impl LocalUsage {
+  fn len(&self) -> usize {
+    match self {
+      LocalUsage::Alternatives(_) => 1,
+      LocalUsage::Range(minimum, maximum) => (maximum-minimum) as usize+1,
+    }
+  }
+  fn get(&self, index: usize) -> Vec<u32> {
+    match self {
+      LocalUsage::Alternatives(usages) => usages.clone(),
+      LocalUsage::Range(minimum, _) => vec![minimum+index as u32],
     }
   }
+  fn last(&self) -> Vec<u32> {
+    self.get(self.len()-1)
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+struct LocalState {
+  usages: Vec<LocalUsage>,
+  usage_minimum: Option<u32>,
+  delimiter: Option<Vec<u32>>,
+  designators: Vec<(u32, u32)>,
+  designator_minimum: Option<u32>,
+  strings: Vec<(u32, u32)>,
+  string_minimum: Option<u32>,
+}
+impl LocalState {
+  fn usage_count(&self) -> usize {
+    self.usages.iter().map(LocalUsage::len).sum()
+  }
+  fn push_usage(&mut self, usage: LocalUsage, index: usize, name: &'static str) -> Result<()> {
+    if let LocalUsage::Range(minimum, maximum) = usage && maximum < minimum {
+      return Ok(());
+    };
+    if self.usage_count()+usage.len() > MAX_REPORT_BITS {
+      return Err(Error::LimitExceeded { index, name });
+    };
+    self.usages.push(usage);
+    Ok(())
+  }
+  fn usage(&self, index: usize) -> Option<Vec<u32>> {
+    let mut index = index;
+    for usage in &self.usages {
+      if index < usage.len() {
+        return Some(usage.get(index));
+      };
+      index -= usage.len();
+    };
+    self.usages.last().map(LocalUsage::last)
+  }
+  fn usage_range(&self) -> Vec<u32> {
+    let mut range = vec![];
+    for usage in &self.usages {
+      match usage {
+        LocalUsage::Alternatives(usages) => range.extend_from_slice(usages),
+        LocalUsage::Range(minimum, maximum) => range.extend(*minimum..=*maximum),
+      };
+    };
+    range
+  }
+}
+fn index_count(indices: &[(u32, u32)]) -> usize {
+  indices.iter().map(|(minimum, maximum)| (maximum-minimum) as usize+1).sum()
+}
+fn push_indices(indices: &mut Vec<(u32, u32)>, minimum: u32, maximum: u32, index: usize, name: &'static str) -> Result<()> {
+  if maximum < minimum {
+    return Ok(());
+  };
+  if index_count(indices)+(maximum-minimum) as usize+1 > MAX_REPORT_BITS {
+    return Err(Error::LimitExceeded { index, name });
+  };
+  indices.push((minimum, maximum));
+  Ok(())
+}
+fn index_at(indices: &[(u32, u32)], index: usize) -> Option<u32> {
+  let mut index = index;
+  for (minimum, maximum) in indices {
+    let count = (maximum-minimum) as usize+1;
+    if index < count {
+      return Some(minimum+index as u32);
+    };
+    index -= count;
+  };
+  indices.last().map(|(_, maximum)| *maximum)
+}
+fn index_range(indices: &[(u32, u32)]) -> Vec<u32> {
+  indices.iter().flat_map(|(minimum, maximum)| *minimum..=*maximum).collect()
 }
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Report {
@@ -73,11 +182,9 @@ pub struct Report {
 impl Report {
   fn into_descriptor_items(self, state: &mut GlobalState) -> Vec<DescriptorItem> {
     let mut sequence = vec![];
-    if let Some(id) = self.id {
-      if Some(id) != state.report_id {
-        sequence.push(DescriptorItem::ReportId(id));
-        state.report_id = Some(id);
-      };
+    if let Some(id) = self.id && Some(id) != state.report_id {
+      sequence.push(DescriptorItem::ReportId(id));
+      state.report_id = Some(id);
     };
     let mut field_sets: Vec<Vec<ReportField>> = vec![];
     if let Some(first_field) = self.fields.first() {
@@ -96,7 +203,9 @@ impl Report {
             physical_maximum: previous_physical_maximum,
             unit_exponent: previous_unit_exponent,
             unit: previous_unit,
-            usages: _,
+            usages: previous_usages,
+            designator_index: previous_designator_index,
+            string_index: previous_string_index,
             constant: previous_constant,
             relative: previous_relative,
             wrap: previous_wrap,
@@ -113,7 +222,9 @@ impl Report {
             physical_maximum,
             unit_exponent,
             unit,
-            usages: _,
+            usages,
+            designator_index,
+            string_index,
             constant,
             relative,
             wrap,
@@ -129,6 +240,9 @@ impl Report {
             previous_physical_maximum == physical_maximum &&
             previous_unit_exponent == unit_exponent &&
             previous_unit == unit &&
+            previous_usages.is_some() == usages.is_some() &&
+            previous_designator_index.is_some() == designator_index.is_some() &&
+            previous_string_index.is_some() == string_index.is_some() &&
             previous_constant == constant &&
             previous_relative == relative &&
             previous_wrap == wrap &&
@@ -151,6 +265,19 @@ impl Report {
       field_sets.push(previous_fields);
     };
     for set in field_sets {
+      if let ReportField::Variable { physical_minimum, physical_maximum, unit_exponent, unit, .. } = set.first().unwrap() &&
+        (physical_minimum.is_none() && state.physical_minimum.is_some() ||
+        physical_maximum.is_none() && state.physical_maximum.is_some() ||
+        unit_exponent.is_none() && state.unit_exponent.is_some() ||
+        unit.is_none() && state.unit.is_some()) &&
+        let Some(scope) = state.scope.take() {
+        sequence.push(DescriptorItem::Pop);
+        *state = *scope;
+        if let Some(id) = self.id && Some(id) != state.report_id {
+          sequence.push(DescriptorItem::ReportId(id));
+          state.report_id = Some(id);
+        };
+      };
       if Some(set.len() as u32) != state.report_count {
         sequence.push(DescriptorItem::ReportCount(set.len() as u32));
         state.report_count = Some(set.len() as u32);
@@ -165,6 +292,8 @@ impl Report {
           unit_exponent,
           unit,
           usages,
+          designator_index,
+          string_index,
           constant,
           relative,
           wrap,
@@ -186,28 +315,31 @@ impl Report {
             sequence.push(DescriptorItem::LogicalMaximum(*logical_maximum));
             state.logical_maximum = Some(*logical_maximum);
           };
-          if *physical_minimum != state.physical_minimum {
-            sequence.push(DescriptorItem::PhysicalMinimum(physical_minimum.unwrap_or(0)));
-            state.physical_minimum = *physical_minimum;
+          if state.scope.is_none() && (physical_minimum.is_some() || physical_maximum.is_some() || unit_exponent.is_some() || unit.is_some()) {
+            sequence.push(DescriptorItem::Push);
+            state.scope = Some(Box::new(state.clone()));
+          };
+          if let Some(minimum) = *physical_minimum && Some(minimum) != state.physical_minimum {
+            sequence.push(DescriptorItem::PhysicalMinimum(minimum));
+            state.physical_minimum = Some(minimum);
           };
-          if *physical_maximum != state.physical_maximum {
-            sequence.push(DescriptorItem::PhysicalMaximum(physical_maximum.unwrap_or(0)));
-            state.physical_maximum = *physical_maximum;
+          if let Some(maximum) = *physical_maximum && Some(maximum) != state.physical_maximum {
+            sequence.push(DescriptorItem::PhysicalMaximum(maximum));
+            state.physical_maximum = Some(maximum);
           };
-          if *unit_exponent != state.unit_exponent {
-            sequence.push(DescriptorItem::UnitExponent(unit_exponent.unwrap_or(0)));
-            state.unit_exponent = *unit_exponent;
+          if let Some(exponent) = *unit_exponent && Some(exponent) != state.unit_exponent {
+            sequence.push(DescriptorItem::UnitExponent(exponent));
+            state.unit_exponent = Some(exponent);
           };
-          if *unit != state.unit {
-            sequence.push(DescriptorItem::Unit(unit.unwrap_or(0)));
-            state.unit = *unit;
+          if let Some(unit) = *unit && Some(unit) != state.unit {
+            sequence.push(DescriptorItem::Unit(unit));
+            state.unit = Some(unit);
           };
           if usages.is_some() {
             let mut usage_sets = vec![];
-            for field in set.clone() {
-              match field {
-                ReportField::Variable { usages, .. } => usage_sets.push(usages.unwrap()),
-                _ => {},
+            for field in &set {
+              if let ReportField::Variable { usages: Some(usages), .. } = field {
+                usage_sets.push(usages.clone());
               };
             };
             let all_usages = usage_sets.clone().into_iter().flatten().collect::<Vec<u32>>();
@@ -215,9 +347,9 @@ impl Report {
             if set.len() == all_usages.len() {
               let mut previous_usage_value = *all_usages.first().unwrap();
               for usage_value in &all_usages[1..] {
-                let previous_usage = Usage::try_from(previous_usage_value).unwrap();
-                let usage = Usage::try_from(*usage_value).unwrap();
-                if previous_usage.usage_id_value()+1 != usage.usage_id_value() || previous_usage.usage_page_value() != usage.usage_page_value() {
+                let previous_usage = UsageValue(previous_usage_value);
+                let usage = UsageValue(*usage_value);
+                if previous_usage.usage_id_value().checked_add(1) != Some(usage.usage_id_value()) || previous_usage.usage_page_value() != usage.usage_page_value() {
                   continuous = false;
                   break;
                 };
@@ -228,8 +360,8 @@ impl Report {
               continuous = false;
             };
             if continuous && set.len() > 1 {
-              let minimum = Usage::try_from(*all_usages.first().unwrap()).unwrap();
-              let maximum = Usage::try_from(*all_usages.last().unwrap()).unwrap();
+              let minimum = UsageValue(*all_usages.first().unwrap());
+              let maximum = UsageValue(*all_usages.last().unwrap());
               if Some(minimum.usage_page_value()) != state.usage_page {
                 sequence.push(DescriptorItem::UsagePage(minimum.usage_page_value()));
                 state.usage_page = Some(minimum.usage_page_value());
@@ -243,7 +375,7 @@ impl Report {
                   sequence.push(DescriptorItem::Delimiter(DelimiterFlag::Open));
                 };
                 for usage_value in usages {
-                  let usage = Usage::try_from(*usage_value).unwrap();
+                  let usage = UsageValue(*usage_value);
                   if Some(usage.usage_page_value()) != state.usage_page {
                     sequence.push(DescriptorItem::UsagePage(usage.usage_page_value()));
                     state.usage_page = Some(usage.usage_page_value());
@@ -253,12 +385,26 @@ impl Report {
                 if usages.len() != 1 {
                   sequence.push(DescriptorItem::Delimiter(DelimiterFlag::Close));
                 };
-                if usage_sets[index..].into_iter().all(|set| set==usages) {
+                if usage_sets[index..].iter().all(|set| set==usages) {
                   break;
                 };
               };
             };
           };
+          if designator_index.is_some() {
+            let designators = set.iter().filter_map(|field| match field {
+              ReportField::Variable { designator_index, .. } => *designator_index,
+              ReportField::Array { .. } => None,
+            }).collect::<Vec<u32>>();
+            sequence.append(&mut write_index_items(&designators, true, DescriptorItem::DesignatorIndex, DescriptorItem::DesignatorMinimum, DescriptorItem::DesignatorMaximum));
+          };
+          if string_index.is_some() {
+            let strings = set.iter().filter_map(|field| match field {
+              ReportField::Variable { string_index, .. } => *string_index,
+              ReportField::Array { .. } => None,
+            }).collect::<Vec<u32>>();
+            sequence.append(&mut write_index_items(&strings, true, DescriptorItem::StringIndex, DescriptorItem::StringMinimum, DescriptorItem::StringMaximum));
+          };
           match self.ty {
             ReportType::Input => sequence.push(DescriptorItem::Input {
               constant: if *constant { ReportConstantFlag::Constant } else { ReportConstantFlag::Data },
@@ -278,7 +424,7 @@ impl Report {
               linear: if *linear { ReportLinearFlag::Linear } else { ReportLinearFlag::NonLinear },
               preferred_state: if *preferred_state { ReportPreferredStateFlag::PreferredState } else { ReportPreferredStateFlag::NoPreferred },
               null_state: if *null_state { ReportNullStateFlag::NullState } else { ReportNullStateFlag::NoNullPosition },
-              volatile: if volatile.unwrap() { ReportVolatileFlag::Volatile } else { ReportVolatileFlag::NonVolatile },
+              volatile: if volatile.unwrap_or(false) { ReportVolatileFlag::Volatile } else { ReportVolatileFlag::NonVolatile },
               buffered_bytes: if *buffered_bytes { ReportBufferedBytesFlag::BufferedBytes } else { ReportBufferedBytesFlag::BitField },
             }),
             ReportType::Feature => sequence.push(DescriptorItem::Feature {
@@ -289,7 +435,7 @@ impl Report {
               linear: if *linear { ReportLinearFlag::Linear } else { ReportLinearFlag::NonLinear },
               preferred_state: if *preferred_state { ReportPreferredStateFlag::PreferredState } else { ReportPreferredStateFlag::NoPreferred },
               null_state: if *null_state { ReportNullStateFlag::NullState } else { ReportNullStateFlag::NoNullPosition },
-              volatile: if volatile.unwrap() { ReportVolatileFlag::Volatile } else { ReportVolatileFlag::NonVolatile },
+              volatile: if volatile.unwrap_or(false) { ReportVolatileFlag::Volatile } else { ReportVolatileFlag::NonVolatile },
               buffered_bytes: if *buffered_bytes { ReportBufferedBytesFlag::BufferedBytes } else { ReportBufferedBytesFlag::BitField },
             }),
           };
@@ -299,6 +445,8 @@ impl Report {
           logical_minimum,
           logical_maximum,
           usage_range,
+          designator_range,
+          string_range,
           constant,
           relative,
         } => {
@@ -314,37 +462,41 @@ impl Report {
             sequence.push(DescriptorItem::LogicalMaximum(*logical_maximum));
             state.logical_maximum = Some(*logical_maximum);
           };
-          let mut continuous = true;
-          let mut previous_usage_value = *usage_range.first().unwrap();
-          for usage_value in &usage_range[1..] {
-            let previous_usage = Usage::try_from(previous_usage_value).unwrap();
-            let usage = Usage::try_from(*usage_value).unwrap();
-            if previous_usage.usage_id_value()+1 != usage.usage_id_value() || previous_usage.usage_page_value() != usage.usage_page_value() {
-              continuous = false;
-              break;
-            };
-            previous_usage_value = *usage_value;
-          };
-          if continuous {
-            let minimum = Usage::try_from(*usage_range.first().unwrap()).unwrap();
-            let maximum = Usage::try_from(*usage_range.first().unwrap()).unwrap();
-            if Some(minimum.usage_page_value()) != state.usage_page {
-              sequence.push(DescriptorItem::UsagePage(minimum.usage_page_value()));
-              state.usage_page = Some(minimum.usage_page_value());
+          if !usage_range.is_empty() {
+            let mut continuous = true;
+            let mut previous_usage_value = *usage_range.first().unwrap();
+            for usage_value in &usage_range[1..] {
+              let previous_usage = UsageValue(previous_usage_value);
+              let usage = UsageValue(*usage_value);
+              if previous_usage.usage_id_value().checked_add(1) != Some(usage.usage_id_value()) || previous_usage.usage_page_value() != usage.usage_page_value() {
+                continuous = false;
+                break;
+              };
+              previous_usage_value = *usage_value;
             };
-            sequence.push(DescriptorItem::UsageMinimum(UsageSpecifier::Usage(minimum.usage_id_value())));
-            sequence.push(DescriptorItem::UsageMaximum(UsageSpecifier::Usage(maximum.usage_id_value())));
-          }
-          else {
-            for usage_value in usage_range {
-              let usage = Usage::try_from(*usage_value).unwrap();
-              if Some(usage.usage_page_value()) != state.usage_page {
-                sequence.push(DescriptorItem::UsagePage(usage.usage_page_value()));
-                state.usage_page = Some(usage.usage_page_value());
+            if continuous {
+              let minimum = UsageValue(*usage_range.first().unwrap());
+              let maximum = UsageValue(*usage_range.last().unwrap());
+              if Some(minimum.usage_page_value()) != state.usage_page {
+                sequence.push(DescriptorItem::UsagePage(minimum.usage_page_value()));
+                state.usage_page = Some(minimum.usage_page_value());
+              };
+              sequence.push(DescriptorItem::UsageMinimum(UsageSpecifier::Usage(minimum.usage_id_value())));
+              sequence.push(DescriptorItem::UsageMaximum(UsageSpecifier::Usage(maximum.usage_id_value())));
+            }
+            else {
+              for usage_value in usage_range {
+                let usage = UsageValue(*usage_value);
+                if Some(usage.usage_page_value()) != state.usage_page {
+                  sequence.push(DescriptorItem::UsagePage(usage.usage_page_value()));
+                  state.usage_page = Some(usage.usage_page_value());
+                };
+                sequence.push(DescriptorItem::Usage(UsageSpecifier::Usage(usage.usage_id_value())));
               };
-              sequence.push(DescriptorItem::Usage(UsageSpecifier::Usage(usage.usage_id_value())));
             };
           };
+          sequence.append(&mut write_index_items(designator_range, false, DescriptorItem::DesignatorIndex, DescriptorItem::DesignatorMinimum, DescriptorItem::DesignatorMaximum));
+          sequence.append(&mut write_index_items(string_range, false, DescriptorItem::StringIndex, DescriptorItem::StringMinimum, DescriptorItem::StringMaximum));
           match self.ty {
             ReportType::Input => sequence.push(DescriptorItem::Input {
               constant: if *constant { ReportConstantFlag::Constant } else { ReportConstantFlag::Data },
@@ -392,10 +544,10 @@ pub struct Collection {
   pub items: Vec<MainItem>,
 }
 impl Collection {
-  fn into_descriptor_items(self, state: &mut GlobalState) -> Vec<DescriptorItem> {
+  fn into_descriptor_items(self, state: &mut GlobalState, stacking: GlobalStacking) -> Result<Vec<DescriptorItem>> {
     let mut sequence = vec![];
     if let Some(usage) = self.usage {
-      let usage = Usage::try_from(usage).unwrap();
+      let usage = UsageValue(usage);
       if Some(usage.usage_page_value()) != state.usage_page {
         sequence.push(DescriptorItem::UsagePage(usage.usage_page_value()));
         state.usage_page = Some(usage.usage_page_value());
@@ -403,11 +555,9 @@ impl Collection {
       sequence.push(DescriptorItem::Usage(UsageSpecifier::Usage(usage.usage_id_value())));
     };
     sequence.push(DescriptorItem::Collection(self.ty));
-    for item in self.items {
-      sequence.append(&mut write_main_item(item, state));
-    };
+    sequence.append(&mut write_main_items(self.items, state, stacking)?);
     sequence.push(DescriptorItem::EndCollection);
-    sequence
+    Ok(sequence)
   }
 }
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -420,18 +570,537 @@ pub struct Descriptor {
   pub items: Vec<MainItem>,
 }
 impl Descriptor {
-  pub fn into_descriptor_items(self) -> Vec<DescriptorItem> {
-    let mut sequence = vec![];
+  pub fn from_descriptor_items(items: Vec<DescriptorItem>) -> Self {
+    Descriptor::try_from_descriptor_items(items).unwrap_or_else(|error| panic!("{error}"))
+  }
+  pub fn try_from_descriptor_items(items: Vec<DescriptorItem>) -> Result<Self> {
     let mut state = GlobalState::default();
-    for item in self.items {
-      sequence.append(&mut write_main_item(item, &mut state));
+    let mut stack: Vec<GlobalState> = vec![];
+    let mut local = LocalState::default();
+    let mut root = vec![];
+    let mut collections: Vec<(usize, Collection)> = vec![];
+    let mut remaining = MAX_REPORT_BITS*64;
+    for (index, item) in items.into_iter().enumerate() {
+      match item {
+        DescriptorItem::Input { .. } | DescriptorItem::Output { .. } | DescriptorItem::Feature { .. } => {
+          let (ty, mut fields) = read_report_fields(item, index, &state, &local, &mut remaining)?;
+          let items = match collections.last_mut() {
+            Some((_, collection)) => &mut collection.items,
+            None => &mut root,
+          };
+          match items.last_mut() {
+            Some(MainItem::Report(report)) if report.ty == ty && report.id == state.report_id => report.fields.append(&mut fields),
+            _ => items.push(MainItem::Report(Report { ty, id: state.report_id, fields })),
+          };
+          local = LocalState::default();
+        },
+        DescriptorItem::Collection(ty) => {
+          collections.push((index, Collection {
+            ty,
+            usage: local.usages.first().and_then(|usage| usage.get(0).first().copied()),
+            items: vec![],
+          }));
+          local = LocalState::default();
+        },
+        DescriptorItem::EndCollection => {
+          let Some((_, collection)) = collections.pop() else {
+            return Err(Error::UnmatchedEndCollection { index });
+          };
+          match collections.last_mut() {
+            Some((_, parent)) => parent.items.push(MainItem::Collection(collection)),
+            None => root.push(MainItem::Collection(collection)),
+          };
+          local = LocalState::default();
+        },
+        DescriptorItem::UsagePage(page) => state.usage_page = Some(page),
+        DescriptorItem::LogicalMinimum(minimum) => state.logical_minimum = Some(minimum),
+        DescriptorItem::LogicalMaximum(maximum) => state.logical_maximum = Some(maximum),
+        DescriptorItem::PhysicalMinimum(minimum) => state.physical_minimum = Some(minimum),
+        DescriptorItem::PhysicalMaximum(maximum) => state.physical_maximum = Some(maximum),
+        DescriptorItem::UnitExponent(exponent) => state.unit_exponent = Some(exponent),
+        DescriptorItem::Unit(unit) => state.unit = Some(unit),
+        DescriptorItem::ReportSize(size) => state.report_size = Some(size),
+        DescriptorItem::ReportId(id) => state.report_id = Some(id),
+        DescriptorItem::ReportCount(count) => state.report_count = Some(count),
+        DescriptorItem::Push => stack.push(state.clone()),
+        DescriptorItem::Pop => state = stack.pop().ok_or(Error::UnmatchedPop { index })?,
+        DescriptorItem::Usage(usage) => {
+          let usage = resolve_usage(usage, &state);
+          match local.delimiter.as_mut() {
+            Some(alternatives) => alternatives.push(usage),
+            None => local.push_usage(LocalUsage::Range(usage, usage), index, "Usage")?,
+          };
+        },
+        DescriptorItem::UsageMinimum(usage) => local.usage_minimum = Some(resolve_usage(usage, &state)),
+        DescriptorItem::UsageMaximum(usage) => {
+          let maximum = resolve_usage(usage, &state);
+          let minimum = local.usage_minimum.take().unwrap_or(maximum);
+          local.push_usage(LocalUsage::Range(minimum, maximum), index, "Usage Maximum")?;
+        },
+        DescriptorItem::DesignatorIndex(designator) => push_indices(&mut local.designators, designator, designator, index, "Designator Index")?,
+        DescriptorItem::DesignatorMinimum(minimum) => local.designator_minimum = Some(minimum),
+        DescriptorItem::DesignatorMaximum(maximum) => {
+          let minimum = local.designator_minimum.take().unwrap_or(maximum);
+          push_indices(&mut local.designators, minimum, maximum, index, "Designator Maximum")?;
+        },
+        DescriptorItem::StringIndex(string) => push_indices(&mut local.strings, string, string, index, "String Index")?,
+        DescriptorItem::StringMinimum(minimum) => local.string_minimum = Some(minimum),
+        DescriptorItem::StringMaximum(maximum) => {
+          let minimum = local.string_minimum.take().unwrap_or(maximum);
+          push_indices(&mut local.strings, minimum, maximum, index, "String Maximum")?;
+        },
+        DescriptorItem::Delimiter(DelimiterFlag::Open) => local.delimiter = Some(vec![]),
+        DescriptorItem::Delimiter(DelimiterFlag::Close) => {
+          if let Some(alternatives) = local.delimiter.take() && !alternatives.is_empty() {
+            local.push_usage(LocalUsage::Alternatives(alternatives), index, "Delimiter")?;
+          };
+        },
+        DescriptorItem::Long { .. } | DescriptorItem::Reserved { .. } => {},
+      };
     };
-    sequence
+    if let Some((index, _)) = collections.last() {
+      return Err(Error::UnclosedCollection { index: *index });
+    };
+    Ok(Descriptor { items: root })
+  }
+  pub fn from_bytes(data: &[u8]) -> Self {
+    Descriptor::try_from_bytes(data).unwrap_or_else(|error| panic!("{error}"))
+  }
+  pub fn try_from_bytes(data: &[u8]) -> Result<Self> {
+    Descriptor::try_from_descriptor_items(items_from_bytes(data).collect::<Result<Vec<_>>>()?)
+  }
+  pub fn reports(&self) -> Vec<Report> {
+    let mut reports: Vec<Report> = vec![];
+    let mut pending: Vec<&MainItem> = self.items.iter().rev().collect();
+    while let Some(item) = pending.pop() {
+      match item {
+        MainItem::Collection(collection) => pending.extend(collection.items.iter().rev()),
+        MainItem::Report(report) => match reports.iter_mut().find(|existing| existing.ty == report.ty && existing.id == report.id) {
+          Some(existing) => existing.fields.extend(report.fields.iter().cloned()),
+          None => reports.push(report.clone()),
+        },
+      };
+    };
+    reports
+  }
+  pub fn into_descriptor_items(self) -> Vec<DescriptorItem> {
+    self.into_descriptor_items_with(GlobalStacking::Never)
+  }
+  pub fn into_descriptor_items_with(self, stacking: GlobalStacking) -> Vec<DescriptorItem> {
+    self.try_into_descriptor_items_with(stacking).unwrap_or_else(|error| panic!("{error}"))
+  }
+  pub fn try_into_descriptor_items_with(self, stacking: GlobalStacking) -> Result<Vec<DescriptorItem>> {
+    match stacking {
+      GlobalStacking::Never => {
+        let mut sequence = write_main_items(self.items, &mut GlobalState::default(), stacking)?;
+        remove_unmatched_pushes(&mut sequence);
+        Ok(sequence)
+      },
+      GlobalStacking::WhenShorter => {
+        let mut plain = write_main_items(self.items.clone(), &mut GlobalState::default(), GlobalStacking::Never)?;
+        let mut stacked = write_main_items(self.items, &mut GlobalState::default(), stacking)?;
+        remove_unmatched_pushes(&mut plain);
+        remove_unmatched_pushes(&mut stacked);
+        if try_items_into_bytes(&stacked)?.len() < try_items_into_bytes(&plain)?.len() {
+          Ok(stacked)
+        }
+        else {
+          Ok(plain)
+        }
+      },
+    }
   }
 }
-fn write_main_item(item: MainItem, state: &mut GlobalState) -> Vec<DescriptorItem> {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GlobalStacking {
+  Never,
+  WhenShorter,
+}
+fn write_main_item(item: MainItem, state: &mut GlobalState, stacking: GlobalStacking) -> Result<Vec<DescriptorItem>> {
   match item {
-    MainItem::Collection(collection) => collection.into_descriptor_items(state),
-    MainItem::Report(report) => report.into_descriptor_items(state),
+    MainItem::Collection(collection) => collection.into_descriptor_items(state, stacking),
+    MainItem::Report(report) => Ok(report.into_descriptor_items(state)),
+  }
+}
+fn write_main_items(items: Vec<MainItem>, state: &mut GlobalState, stacking: GlobalStacking) -> Result<Vec<DescriptorItem>> {
+  let mut sequence = vec![];
+  for (index, item) in items.iter().enumerate() {
+    let MainItem::Collection(collection) = item else {
+      sequence.append(&mut write_main_item(item.clone(), state, stacking)?);
+      continue;
+    };
+    if stacking == GlobalStacking::Never || index+1 == items.len() {
+      sequence.append(&mut write_main_item(item.clone(), state, stacking)?);
+      continue;
+    };
+    let saved_state = state.clone();
+    let mut collection_items = collection.clone().into_descriptor_items(state, stacking)?;
+    let rest = items[index+1..].to_vec();
+    let plain_rest = write_main_items(rest.clone(), &mut state.clone(), GlobalStacking::Never)?;
+    let stacked_rest = write_main_items(rest, &mut saved_state.clone(), GlobalStacking::Never)?;
+    if let Some(pushes) = unmatched_pushes(&collection_items) && try_items_into_bytes(&stacked_rest)?.len()+2 < try_items_into_bytes(&plain_rest)?.len() {
+      for index in pushes.into_iter().rev() {
+        collection_items.remove(index);
+      };
+      sequence.push(DescriptorItem::Push);
+      sequence.append(&mut collection_items);
+      sequence.push(DescriptorItem::Pop);
+      *state = saved_state;
+    }
+    else {
+      sequence.append(&mut collection_items);
+    };
+  };
+  Ok(sequence)
+}
+fn unmatched_pushes(sequence: &[DescriptorItem]) -> Option<Vec<usize>> {
+  let mut pushes = vec![];
+  for (index, item) in sequence.iter().enumerate() {
+    match item {
+      DescriptorItem::Push => pushes.push(index),
+      DescriptorItem::Pop => {
+        pushes.pop()?;
+      },
+      _ => {},
+    };
+  };
+  Some(pushes)
+}
+fn remove_unmatched_pushes(sequence: &mut Vec<DescriptorItem>) {
+  if let Some(pushes) = unmatched_pushes(sequence) {
+    for index in pushes.into_iter().rev() {
+      sequence.remove(index);
+    };
+  };
+}
+fn write_index_items(indices: &[u32], repeat_last: bool, index_item: fn(u32) -> DescriptorItem, minimum_item: fn(u32) -> DescriptorItem, maximum_item: fn(u32) -> DescriptorItem) -> Vec<DescriptorItem> {
+  let mut sequence = vec![];
+  let continuous = indices.windows(2).all(|pair| pair[0].checked_add(1) == Some(pair[1]));
+  if indices.len() > 1 && continuous {
+    sequence.push(minimum_item(*indices.first().unwrap()));
+    sequence.push(maximum_item(*indices.last().unwrap()));
+    return sequence;
+  };
+  for (position, index) in indices.iter().enumerate() {
+    sequence.push(index_item(*index));
+    if repeat_last && indices[position..].iter().all(|other| other == index) {
+      break;
+    };
+  };
+  sequence
+}
+fn resolve_usage(usage: UsageSpecifier, state: &GlobalState) -> u32 {
+  match usage {
+    UsageSpecifier::Usage(usage) => ((state.usage_page.unwrap_or(0) as u32) << 16) | usage as u32,
+    UsageSpecifier::ExtendedUsage(usage) => usage,
+  }
+}
+fn read_report_fields(item: DescriptorItem, index: usize, state: &GlobalState, local: &LocalState, remaining: &mut usize) -> Result<(ReportType, Vec<ReportField>)> {
+  let (ty, constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes) = match item {
+    DescriptorItem::Input { constant, layout, relative, wrap, linear, preferred_state, null_state, buffered_bytes } => (ReportType::Input, constant, layout, relative, wrap, linear, preferred_state, null_state, None, buffered_bytes),
+    DescriptorItem::Output { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes } => (ReportType::Output, constant, layout, relative, wrap, linear, preferred_state, null_state, Some(volatile), buffered_bytes),
+    DescriptorItem::Feature { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes } => (ReportType::Feature, constant, layout, relative, wrap, linear, preferred_state, null_state, Some(volatile), buffered_bytes),
+    _ => unreachable!(),
+  };
+  let size = state.report_size.ok_or(Error::UndefinedGlobal { index, name: "Report Size" })?;
+  let count = state.report_count.ok_or(Error::UndefinedGlobal { index, name: "Report Count" })?;
+  let logical_minimum = state.logical_minimum.ok_or(Error::UndefinedGlobal { index, name: "Logical Minimum" })?;
+  let logical_maximum = state.logical_maximum.ok_or(Error::UndefinedGlobal { index, name: "Logical Maximum" })?;
+  let count = count as usize;
+  if count > MAX_REPORT_BITS || count.saturating_mul(size as usize) > MAX_REPORT_BITS {
+    return Err(Error::LimitExceeded { index, name: "Report Count" });
+  };
+  let entries = match layout {
+    ReportLayoutFlag::Variable => count,
+    ReportLayoutFlag::Array => count*(1+local.usage_count()+index_count(&local.designators)+index_count(&local.strings)),
+  };
+  *remaining = remaining.checked_sub(entries).ok_or(Error::LimitExceeded { index, name: "Report Count" })?;
+  let mut fields = Vec::with_capacity(count);
+  for index in 0..count {
+    fields.push(match layout {
+      ReportLayoutFlag::Variable => ReportField::Variable {
+        size,
+        logical_minimum,
+        logical_maximum,
+        physical_minimum: state.physical_minimum,
+        physical_maximum: state.physical_maximum,
+        unit_exponent: state.unit_exponent,
+        unit: state.unit,
+        usages: local.usage(index),
+        designator_index: index_at(&local.designators, index),
+        string_index: index_at(&local.strings, index),
+        constant: constant == ReportConstantFlag::Constant,
+        relative: relative == ReportRelativeFlag::Relative,
+        wrap: wrap == ReportWrapFlag::Wrap,
+        linear: linear == ReportLinearFlag::Linear,
+        preferred_state: preferred_state == ReportPreferredStateFlag::PreferredState,
+        null_state: null_state == ReportNullStateFlag::NullState,
+        volatile: volatile.map(|volatile| volatile == ReportVolatileFlag::Volatile),
+        buffered_bytes: buffered_bytes == ReportBufferedBytesFlag::BufferedBytes,
+      },
+      ReportLayoutFlag::Array => ReportField::Array {
+        size,
+        logical_minimum,
+        logical_maximum,
+        usage_range: local.usage_range(),
+        designator_range: index_range(&local.designators),
+        string_range: index_range(&local.strings),
+        constant: constant == ReportConstantFlag::Constant,
+        relative: relative == ReportRelativeFlag::Relative,
+      },
+    });
+  };
+  Ok((ty, fields))
+}
+pub const BOOT_KEYBOARD_DESCRIPTOR: [u8; 63] = [
+  0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x05, 0x07,
+  0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01,
+  0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01,
+  0x75, 0x08, 0x81, 0x01, 0x95, 0x05, 0x75, 0x01,
+  0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02,
+  0x95, 0x01, 0x75, 0x03, 0x91, 0x01, 0x95, 0x06,
+  0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07,
+  0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xC0,
+];
+pub const BOOT_MOUSE_DESCRIPTOR: [u8; 50] = [
+  0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01,
+  0xA1, 0x00, 0x95, 0x03, 0x75, 0x01, 0x05, 0x09,
+  0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01,
+  0x81, 0x02, 0x95, 0x01, 0x75, 0x05, 0x81, 0x01,
+  0x75, 0x08, 0x95, 0x02, 0x05, 0x01, 0x09, 0x30,
+  0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x81, 0x06,
+  0xC0, 0xC0,
+];
+pub const WHEEL_MOUSE_DESCRIPTOR: [u8; 52] = [
+  0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01,
+  0xA1, 0x00, 0x95, 0x03, 0x75, 0x01, 0x05, 0x09,
+  0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01,
+  0x81, 0x02, 0x95, 0x01, 0x75, 0x05, 0x81, 0x01,
+  0x75, 0x08, 0x95, 0x03, 0x05, 0x01, 0x09, 0x30,
+  0x09, 0x31, 0x09, 0x38, 0x15, 0x81, 0x25, 0x7F,
+  0x81, 0x06, 0xC0, 0xC0,
+];
+impl Descriptor {
+  pub fn boot_keyboard() -> Self {
+    Descriptor::from_bytes(&BOOT_KEYBOARD_DESCRIPTOR)
+  }
+  pub fn boot_mouse() -> Self {
+    Descriptor::from_bytes(&BOOT_MOUSE_DESCRIPTOR)
+  }
+  pub fn wheel_mouse() -> Self {
+    Descriptor::from_bytes(&WHEEL_MOUSE_DESCRIPTOR)
+  }
+  pub(crate) fn report(&self, ty: ReportType, id: Option<u8>) -> Option<Report> {
+    self.reports().into_iter().find(|report| report.ty == ty && report.id == id)
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const MOUSE: [u8; 52] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01,
+    0xA1, 0x00, 0x05, 0x09, 0x19, 0x01, 0x29, 0x03,
+    0x15, 0x00, 0x25, 0x01, 0x95, 0x03, 0x75, 0x01,
+    0x81, 0x02, 0x95, 0x01, 0x75, 0x05, 0x81, 0x03,
+    0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x09, 0x38,
+    0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x03,
+    0x81, 0x06, 0xC0, 0xC0,
+  ];
+  fn input() -> DescriptorItem {
+    DescriptorItem::Input { constant: ReportConstantFlag::Data, layout: ReportLayoutFlag::Variable, relative: ReportRelativeFlag::Absolute, wrap: ReportWrapFlag::NoWrap, linear: ReportLinearFlag::Linear, preferred_state: ReportPreferredStateFlag::PreferredState, null_state: ReportNullStateFlag::NoNullPosition, buffered_bytes: ReportBufferedBytesFlag::BitField }
+  }
+  fn assert_round_trip(descriptor: &Descriptor) {
+    for stacking in [GlobalStacking::Never, GlobalStacking::WhenShorter] {
+      let items = descriptor.clone().into_descriptor_items_with(stacking);
+      assert_eq!(Descriptor::try_from_descriptor_items(items.clone()).unwrap(), *descriptor);
+      assert_eq!(Descriptor::try_from_bytes(&items_into_bytes(&items)).unwrap(), *descriptor);
+    };
+  }
+  #[test]
+  fn items_descriptor_items_round_trip() {
+    let descriptor = Descriptor::try_from_bytes(&MOUSE).unwrap();
+    assert_round_trip(&descriptor);
+    let items = descriptor.clone().into_descriptor_items();
+    assert_eq!(Descriptor::from_descriptor_items(items.clone()).into_descriptor_items(), items);
+  }
+  #[test]
+  fn rejects_unencodable_fields() {
+    let items = vec![DescriptorItem::UnitExponent(-2), DescriptorItem::LogicalMinimum(0), DescriptorItem::LogicalMaximum(1), DescriptorItem::ReportSize(1), DescriptorItem::ReportCount(1), input()];
+    let mut descriptor = Descriptor::try_from_descriptor_items(items).unwrap();
+    let MainItem::Report(report) = &mut descriptor.items[0] else {
+      panic!("expected a report");
+    };
+    let ReportField::Variable { unit_exponent, .. } = &mut report.fields[0] else {
+      panic!("expected a variable field");
+    };
+    *unit_exponent = Some(16);
+    assert_eq!(descriptor.try_into_descriptor_items_with(GlobalStacking::WhenShorter), Err(Error::UnitExponentOutOfRange { exponent: 16 }));
+  }
+  #[test]
+  fn parses_mouse_structure() {
+    let descriptor = Descriptor::try_from_bytes(&MOUSE).unwrap();
+    let [MainItem::Collection(application)] = &descriptor.items[..] else {
+      panic!("expected one application collection");
+    };
+    assert_eq!(application.ty, CollectionType::Application);
+    assert_eq!(application.usage, Some(0x0001_0002));
+    let reports = descriptor.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].fields.len(), 7);
+  }
+  #[test]
+  fn writes_fields_with_and_without_usages() {
+    let items = vec![
+      DescriptorItem::UsagePage(1),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0x30)),
+      DescriptorItem::LogicalMinimum(0),
+      DescriptorItem::LogicalMaximum(255),
+      DescriptorItem::ReportSize(8),
+      DescriptorItem::ReportCount(1),
+      input(),
+      input(),
+    ];
+    let descriptor = Descriptor::try_from_descriptor_items(items).unwrap();
+    assert_round_trip(&descriptor);
+  }
+  #[test]
+  fn usage_zero_keeps_its_page() {
+    let items = vec![
+      DescriptorItem::UsagePage(7),
+      DescriptorItem::UsageMinimum(UsageSpecifier::Usage(0)),
+      DescriptorItem::UsageMaximum(UsageSpecifier::Usage(0x65)),
+      DescriptorItem::LogicalMinimum(0),
+      DescriptorItem::LogicalMaximum(0x65),
+      DescriptorItem::ReportSize(8),
+      DescriptorItem::ReportCount(1),
+      DescriptorItem::Input { constant: ReportConstantFlag::Data, layout: ReportLayoutFlag::Array, relative: ReportRelativeFlag::Absolute, wrap: ReportWrapFlag::NoWrap, linear: ReportLinearFlag::Linear, preferred_state: ReportPreferredStateFlag::PreferredState, null_state: ReportNullStateFlag::NoNullPosition, buffered_bytes: ReportBufferedBytesFlag::BitField },
+    ];
+    let bytes = items_into_bytes(&items);
+    assert_eq!(&bytes[2..3], &[0x18]);
+    assert_eq!(items_from_bytes(&bytes).collect::<Result<Vec<_>>>().unwrap(), items);
+    assert_round_trip(&Descriptor::try_from_descriptor_items(items).unwrap());
+  }
+  #[test]
+  fn usage_ids_at_the_end_of_a_page() {
+    let items = vec![
+      DescriptorItem::UsagePage(0xFF00),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0xFFFF)),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0)),
+      DescriptorItem::LogicalMinimum(0),
+      DescriptorItem::LogicalMaximum(1),
+      DescriptorItem::ReportSize(1),
+      DescriptorItem::ReportCount(2),
+      input(),
+    ];
+    assert_round_trip(&Descriptor::try_from_descriptor_items(items).unwrap());
+  }
+  #[test]
+  fn rejects_unbalanced_collections() {
+    assert_eq!(Descriptor::try_from_descriptor_items(vec![DescriptorItem::EndCollection]), Err(Error::UnmatchedEndCollection { index: 0 }));
+    assert_eq!(Descriptor::try_from_descriptor_items(vec![DescriptorItem::Collection(CollectionType::Application)]), Err(Error::UnclosedCollection { index: 0 }));
+  }
+  #[test]
+  fn variable_fields_repeat_the_last_usage() {
+    let descriptor = Descriptor::try_from_bytes(&[0x05, 0x09, 0x19, 0x01, 0x29, 0x02, 0x09, 0x05, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x05, 0x81, 0x02]).unwrap();
+    let usages: Vec<_> = descriptor.reports()[0].fields.iter().map(|field| match field {
+      ReportField::Variable { usages, .. } => usages.clone(),
+      ReportField::Array { .. } => None,
+    }).collect();
+    assert_eq!(usages, [Some(vec![0x0009_0001]), Some(vec![0x0009_0002]), Some(vec![0x0009_0005]), Some(vec![0x0009_0005]), Some(vec![0x0009_0005])]);
+  }
+  #[test]
+  fn rejects_hostile_usage_ranges() {
+    let data = [0x05, 0x01, 0x19, 0x00, 0x2B, 0xFF, 0xFF, 0xFF, 0xFF, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x01, 0x81, 0x02];
+    assert_eq!(Descriptor::try_from_bytes(&data), Err(Error::LimitExceeded { index: 2, name: "Usage Maximum" }));
+    let data = [0x49, 0x00, 0x5B, 0xFF, 0xFF, 0xFF, 0xFF, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x01, 0x81, 0x02];
+    assert_eq!(Descriptor::try_from_bytes(&data), Err(Error::LimitExceeded { index: 1, name: "Designator Maximum" }));
+  }
+  #[test]
+  fn rejects_hostile_report_counts() {
+    let data = [0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x97, 0xFF, 0xFF, 0xFF, 0x7F, 0x81, 0x02];
+    assert_eq!(Descriptor::try_from_bytes(&data), Err(Error::LimitExceeded { index: 4, name: "Report Count" }));
+    let data = [0x15, 0x00, 0x25, 0x01, 0x77, 0xFF, 0xFF, 0x00, 0x00, 0x95, 0x10, 0x81, 0x02];
+    assert_eq!(Descriptor::try_from_bytes(&data), Err(Error::LimitExceeded { index: 4, name: "Report Count" }));
+    let mut data = vec![0x05, 0x10, 0x19, 0x00, 0x2A, 0xFF, 0xFF, 0x15, 0x00, 0x25, 0x01, 0x75, 0x00, 0x97, 0x00, 0x00, 0x02, 0x00];
+    data.extend([0x81, 0x00].repeat(64));
+    assert!(matches!(Descriptor::try_from_bytes(&data), Err(Error::LimitExceeded { name: "Report Count", .. })));
+  }
+  #[test]
+  fn scoped_globals_round_trip() {
+    let data = [
+      0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x15, 0x00,
+      0x25, 0x7F, 0x75, 0x08, 0x95, 0x01, 0xA4, 0x35,
+      0x00, 0x46, 0xE8, 0x03, 0x65, 0x11, 0x09, 0x30,
+      0x81, 0x02, 0xB4, 0x09, 0x31, 0x81, 0x02, 0xA1,
+      0x00, 0x55, 0xFE, 0x09, 0x32, 0x81, 0x02, 0xC0,
+      0x09, 0x38, 0x81, 0x02, 0xC0,
+    ];
+    let descriptor = Descriptor::try_from_bytes(&data).unwrap();
+    let physical = descriptor.reports()[0].fields.iter().map(|field| match field {
+      ReportField::Variable { physical_minimum, physical_maximum, unit_exponent, .. } => (*physical_minimum, *physical_maximum, *unit_exponent),
+      ReportField::Array { .. } => panic!("expected a variable field"),
+    }).collect::<Vec<_>>();
+    assert_eq!(physical, [(Some(0), Some(1000), None), (None, None, None), (None, None, Some(-2)), (None, None, Some(-2))]);
+    assert_round_trip(&descriptor);
+    let items = descriptor.clone().into_descriptor_items();
+    assert_eq!(items.iter().filter(|item| **item == DescriptorItem::Push).count(), 1);
+    assert_eq!(items.iter().filter(|item| **item == DescriptorItem::Pop).count(), 1);
+    assert!(Descriptor::try_from_bytes(&[0xB4]).is_err());
+    let data = [
+      0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x15, 0x81,
+      0x25, 0x7F, 0x75, 0x08, 0x95, 0x01, 0x09, 0x38,
+      0x81, 0x02, 0xA1, 0x00, 0xA4, 0x35, 0x81, 0x45,
+      0x7F, 0x16, 0x00, 0x80, 0x26, 0xFF, 0x7F, 0x75,
+      0x10, 0x09, 0x30, 0x81, 0x02, 0xB4, 0xC0, 0x09,
+      0x31, 0x81, 0x02, 0xC0,
+    ];
+    let descriptor = Descriptor::try_from_bytes(&data).unwrap();
+    assert_round_trip(&descriptor);
+    let plain = descriptor.clone().into_descriptor_items();
+    let stacked = descriptor.into_descriptor_items_with(GlobalStacking::WhenShorter);
+    assert!(items_into_bytes(&stacked).len() < items_into_bytes(&plain).len());
+    assert_eq!(stacked.iter().filter(|item| **item == DescriptorItem::Push).count(), 1);
+  }
+  #[test]
+  fn designator_and_string_indices_round_trip() {
+    let data = [
+      0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x09, 0x32,
+      0x39, 0x02, 0x89, 0x04, 0x99, 0x06, 0x15, 0x00,
+      0x25, 0x7F, 0x75, 0x08, 0x95, 0x03, 0x81, 0x02,
+      0x05, 0x09, 0x19, 0x01, 0x29, 0x02, 0x79, 0x01,
+      0x79, 0x03, 0x25, 0x02, 0x95, 0x01, 0x81, 0x00,
+    ];
+    let descriptor = Descriptor::try_from_bytes(&data).unwrap();
+    let fields = &descriptor.reports()[0].fields;
+    let indices = fields[..3].iter().map(|field| match field {
+      ReportField::Variable { designator_index, string_index, .. } => (*designator_index, *string_index),
+      ReportField::Array { .. } => panic!("expected a variable field"),
+    }).collect::<Vec<_>>();
+    assert_eq!(indices, [(Some(2), Some(4)), (Some(2), Some(5)), (Some(2), Some(6))]);
+    let ReportField::Array { designator_range, string_range, .. } = &fields[3] else {
+      panic!("expected an array field");
+    };
+    assert_eq!((designator_range, string_range), (&vec![], &vec![1, 3]));
+    assert_round_trip(&descriptor);
+    let items = descriptor.into_descriptor_items();
+    assert!(items.windows(2).any(|pair| pair == [DescriptorItem::StringMinimum(4), DescriptorItem::StringMaximum(6)]));
+    assert_eq!(items.iter().filter(|item| **item == DescriptorItem::DesignatorIndex(2)).count(), 1);
+    assert!(items.windows(2).any(|pair| pair == [DescriptorItem::StringIndex(1), DescriptorItem::StringIndex(3)]));
+  }
+  #[test]
+  fn boot_templates_round_trip() {
+    for data in [&BOOT_KEYBOARD_DESCRIPTOR[..], &BOOT_MOUSE_DESCRIPTOR, &WHEEL_MOUSE_DESCRIPTOR] {
+      let items = encoded_items_from_bytes(data).collect::<Result<Vec<_>>>().unwrap();
+      assert_eq!(encoded_items_into_bytes(&items, ItemEncoding::Original), data);
+      let descriptor = Descriptor::try_from_bytes(data).unwrap();
+      assert_round_trip(&descriptor);
+    };
+  }
+  #[test]
+  fn boot_template_layouts() {
+    let lengths = |descriptor: Descriptor| descriptor.reports().iter().map(|report| (report.ty, report.layout().bit_length)).collect::<Vec<_>>();
+    assert_eq!(lengths(Descriptor::boot_keyboard()), [(ReportType::Input, 64), (ReportType::Output, 8)]);
+    assert_eq!(lengths(Descriptor::boot_mouse()), [(ReportType::Input, 24)]);
+    assert_eq!(lengths(Descriptor::wheel_mouse()), [(ReportType::Input, 32)]);
   }
 }
@@ -0,0 +1,381 @@
+use std::fmt;
+use crate::descriptor_items::*;
+use crate::descriptors::*;
+use crate::display::{collection_type_name, usage_name};
+use crate::error::Result;
+use crate::layout::ReportLayout;
+use crate::reports::element_usages;
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldLocation {
+  pub ty: ReportType,
+  pub id: Option<u8>,
+  pub field: usize,
+  pub usage: Option<u32>,
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Change {
+  ReportAdded { ty: ReportType, id: Option<u8>, bit_length: usize },
+  ReportRemoved { ty: ReportType, id: Option<u8>, bit_length: usize },
+  ReportLengthChanged { ty: ReportType, id: Option<u8>, old: usize, new: usize },
+  FieldAdded { location: FieldLocation, bit_offset: usize },
+  FieldRemoved { location: FieldLocation, bit_offset: usize },
+  FieldMoved { location: FieldLocation, old: usize, new: usize },
+  FieldSizeChanged { location: FieldLocation, old: u32, new: u32 },
+  FieldKindChanged { location: FieldLocation, old: &'static str, new: &'static str },
+  LogicalRangeChanged { location: FieldLocation, old: (i32, i32), new: (i32, i32) },
+  PhysicalRangeChanged { location: FieldLocation, old: (Option<i32>, Option<i32>), new: (Option<i32>, Option<i32>) },
+  UsagesChanged { location: FieldLocation, old: Vec<u32>, new: Vec<u32> },
+  FlagsChanged { location: FieldLocation, old: String, new: String },
+  CollectionAdded { path: Vec<(CollectionType, Option<u32>)> },
+  CollectionRemoved { path: Vec<(CollectionType, Option<u32>)> },
+  CollectionChanged { path: Vec<(CollectionType, Option<u32>)>, old: (CollectionType, Option<u32>), new: (CollectionType, Option<u32>) },
+}
+fn report_name(ty: ReportType, id: Option<u8>) -> String {
+  match id {
+    Some(id) => format!("{ty:?} report {id}"),
+    None => format!("Unnumbered {ty:?} report"),
+  }
+}
+impl fmt::Display for FieldLocation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} field {}", report_name(self.ty, self.id), self.field)?;
+    if let Some(usage) = self.usage {
+      write!(f, " ({})", usage_name(usage))?;
+    };
+    Ok(())
+  }
+}
+fn collection_name((ty, usage): (CollectionType, Option<u32>)) -> String {
+  match usage {
+    Some(usage) => format!("{} ({})", collection_type_name(ty), usage_name(usage)),
+    None => collection_type_name(ty),
+  }
+}
+fn collection_path(path: &[(CollectionType, Option<u32>)]) -> String {
+  path.iter().map(|collection| collection_name(*collection)).collect::<Vec<String>>().join(" > ")
+}
+fn usage_list(usages: &[u32]) -> String {
+  match usages {
+    [] => "none".to_string(),
+    [first, .., last] if usages.len() > 4 => format!("{} .. {} ({} usages)", usage_name(*first), usage_name(*last), usages.len()),
+    usages => usages.iter().map(|usage| usage_name(*usage)).collect::<Vec<String>>().join(", "),
+  }
+}
+fn range_value(value: Option<i32>) -> String {
+  value.map_or("none".to_string(), |value| value.to_string())
+}
+impl fmt::Display for Change {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Change::ReportAdded { ty, id, bit_length } => write!(f, "+ {} ({bit_length} bits)", report_name(*ty, *id)),
+      Change::ReportRemoved { ty, id, bit_length } => write!(f, "- {} ({bit_length} bits)", report_name(*ty, *id)),
+      Change::ReportLengthChanged { ty, id, old, new } => write!(f, "~ {}: length {old} -> {new} bits", report_name(*ty, *id)),
+      Change::FieldAdded { location, bit_offset } => write!(f, "+ {location} at bit {bit_offset}"),
+      Change::FieldRemoved { location, bit_offset } => write!(f, "- {location} at bit {bit_offset}"),
+      Change::FieldMoved { location, old, new } => write!(f, "~ {location}: offset {old} -> {new}"),
+      Change::FieldSizeChanged { location, old, new } => write!(f, "~ {location}: size {old} -> {new} bits"),
+      Change::FieldKindChanged { location, old, new } => write!(f, "~ {location}: {old} -> {new}"),
+      Change::LogicalRangeChanged { location, old, new } => write!(f, "~ {location}: logical range {}..={} -> {}..={}", old.0, old.1, new.0, new.1),
+      Change::PhysicalRangeChanged { location, old, new } => write!(f, "~ {location}: physical range {}..={} -> {}..={}", range_value(old.0), range_value(old.1), range_value(new.0), range_value(new.1)),
+      Change::UsagesChanged { location, old, new } => write!(f, "~ {location}: usages {} -> {}", usage_list(old), usage_list(new)),
+      Change::FlagsChanged { location, old, new } => write!(f, "~ {location}: flags {old} -> {new}"),
+      Change::CollectionAdded { path } => write!(f, "+ Collection {}", collection_path(path)),
+      Change::CollectionRemoved { path } => write!(f, "- Collection {}", collection_path(path)),
+      Change::CollectionChanged { path, old, new } => write!(f, "~ Collection {}: {} -> {}", collection_path(path), collection_name(*old), collection_name(*new)),
+    }
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct DescriptorDiff {
+  pub changes: Vec<Change>,
+}
+impl DescriptorDiff {
+  pub fn is_empty(&self) -> bool {
+    self.changes.is_empty()
+  }
+}
+impl fmt::Display for DescriptorDiff {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for change in &self.changes {
+      writeln!(f, "{change}")?;
+    };
+    Ok(())
+  }
+}
+fn align<T, K: PartialEq>(old: &[T], new: &[T], key: impl Fn(&T) -> K) -> Vec<(Option<usize>, Option<usize>)> {
+  let old_keys: Vec<K> = old.iter().map(&key).collect();
+  let new_keys: Vec<K> = new.iter().map(&key).collect();
+  let prefix = old_keys.iter().zip(&new_keys).take_while(|(old, new)| old == new).count();
+  let suffix = old_keys[prefix..].iter().rev().zip(new_keys[prefix..].iter().rev()).take_while(|(old, new)| old == new).count();
+  let (old_end, new_end) = (old.len()-suffix, new.len()-suffix);
+  let mut lengths = vec![vec![0usize; new_end-prefix+1]; old_end-prefix+1];
+  for i in (prefix..old_end).rev() {
+    for j in (prefix..new_end).rev() {
+      let (row, column) = (i-prefix, j-prefix);
+      lengths[row][column] = if old_keys[i] == new_keys[j] { lengths[row+1][column+1]+1 } else { lengths[row+1][column].max(lengths[row][column+1]) };
+    };
+  };
+  let mut pairs: Vec<(Option<usize>, Option<usize>)> = (0..prefix).map(|index| (Some(index), Some(index))).collect();
+  let (mut i, mut j) = (prefix, prefix);
+  let mut removed = vec![];
+  let mut added = vec![];
+  let flush = |pairs: &mut Vec<(Option<usize>, Option<usize>)>, removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+    let paired = removed.len().min(added.len());
+    for (old, new) in removed.iter().zip(added.iter()) {
+      pairs.push((Some(*old), Some(*new)));
+    };
+    pairs.extend(removed[paired..].iter().map(|old| (Some(*old), None)));
+    pairs.extend(added[paired..].iter().map(|new| (None, Some(*new))));
+    removed.clear();
+    added.clear();
+  };
+  while i < old_end || j < new_end {
+    if i < old_end && j < new_end && old_keys[i] == new_keys[j] {
+      flush(&mut pairs, &mut removed, &mut added);
+      pairs.push((Some(i), Some(j)));
+      i += 1;
+      j += 1;
+    }
+    else if j < new_end && (i == old_end || lengths[i-prefix][j-prefix+1] >= lengths[i-prefix+1][j-prefix]) {
+      added.push(j);
+      j += 1;
+    }
+    else {
+      removed.push(i);
+      i += 1;
+    };
+  };
+  flush(&mut pairs, &mut removed, &mut added);
+  pairs.extend((0..suffix).map(|index| (Some(old_end+index), Some(new_end+index))));
+  pairs
+}
+fn field_kind(field: &ReportField) -> &'static str {
+  match field {
+    ReportField::Variable { .. } => "Variable",
+    ReportField::Array { .. } => "Array",
+  }
+}
+fn field_usages(field: &ReportField) -> Vec<u32> {
+  match field {
+    ReportField::Variable { usages, .. } => usages.clone().unwrap_or_default(),
+    ReportField::Array { usage_range, .. } => usage_range.clone(),
+  }
+}
+fn field_logical_range(field: &ReportField) -> (i32, i32) {
+  match *field {
+    ReportField::Variable { logical_minimum, logical_maximum, .. } | ReportField::Array { logical_minimum, logical_maximum, .. } => (logical_minimum, logical_maximum),
+  }
+}
+fn field_flags(field: &ReportField) -> String {
+  let (constant, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes, variable) = match *field {
+    ReportField::Variable { constant, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes, .. } => (constant, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes, true),
+    ReportField::Array { constant, relative, .. } => (constant, relative, false, true, true, false, None, false, false),
+  };
+  let mut flags = vec![
+    if constant { "Const" } else { "Data" },
+    if variable { "Var" } else { "Array" },
+    if relative { "Rel" } else { "Abs" },
+  ];
+  if wrap {
+    flags.push("Wrap");
+  };
+  if !linear {
+    flags.push("NonLin");
+  };
+  if !preferred_state {
+    flags.push("NoPref");
+  };
+  if null_state {
+    flags.push("Null");
+  };
+  if volatile == Some(true) {
+    flags.push("Vol");
+  };
+  if buffered_bytes {
+    flags.push("Buf");
+  };
+  flags.join(", ")
+}
+fn diff_fields(changes: &mut Vec<Change>, old: &Report, new: &Report) {
+  let (old_layout, new_layout): (ReportLayout, ReportLayout) = (old.layout(), new.layout());
+  let (old_usages, new_usages) = (element_usages(old), element_usages(new));
+  let key = |report: &Report, usages: &[Option<u32>], index: usize| -> (&'static str, Option<u32>, Vec<u32>) {
+    let field = &report.fields[index];
+    match field {
+      ReportField::Variable { .. } => (field_kind(field), usages[index], vec![]),
+      ReportField::Array { usage_range, .. } => (field_kind(field), None, usage_range.clone()),
+    }
+  };
+  let old_keys: Vec<_> = (0..old.fields.len()).map(|index| key(old, &old_usages, index)).collect();
+  let new_keys: Vec<_> = (0..new.fields.len()).map(|index| key(new, &new_usages, index)).collect();
+  let group_range = |layout: &ReportLayout, group: Option<usize>| group.map_or(0..0, |group| layout.fields[group].field..layout.fields[group].field+layout.fields[group].count as usize);
+  let old_group_keys: Vec<_> = old_layout.fields.iter().map(|group| old_keys[group.field].clone()).collect();
+  let new_group_keys: Vec<_> = new_layout.fields.iter().map(|group| new_keys[group.field].clone()).collect();
+  let alignment: Vec<_> = align(&old_group_keys, &new_group_keys, Clone::clone).into_iter().flat_map(|(old_group, new_group)| {
+    let (old_range, new_range) = (group_range(&old_layout, old_group), group_range(&new_layout, new_group));
+    align(&old_keys[old_range.clone()], &new_keys[new_range.clone()], Clone::clone).into_iter().map(move |(old_index, new_index)| (old_index.map(|index| index+old_range.start), new_index.map(|index| index+new_range.start)))
+  }).collect();
+  for pair in alignment {
+    match pair {
+      (Some(index), None) => changes.push(Change::FieldRemoved {
+        location: FieldLocation { ty: old.ty, id: old.id, field: index, usage: old_usages[index] },
+        bit_offset: old_layout.field_bit_offset(index).unwrap_or(0),
+      }),
+      (None, Some(index)) => changes.push(Change::FieldAdded {
+        location: FieldLocation { ty: new.ty, id: new.id, field: index, usage: new_usages[index] },
+        bit_offset: new_layout.field_bit_offset(index).unwrap_or(0),
+      }),
+      (Some(old_index), Some(new_index)) => {
+        let (old_field, new_field) = (&old.fields[old_index], &new.fields[new_index]);
+        let location = FieldLocation { ty: new.ty, id: new.id, field: new_index, usage: new_usages[new_index] };
+        let (old_offset, new_offset) = (old_layout.field_bit_offset(old_index).unwrap_or(0), new_layout.field_bit_offset(new_index).unwrap_or(0));
+        if old_offset != new_offset {
+          changes.push(Change::FieldMoved { location: location.clone(), old: old_offset, new: new_offset });
+        };
+        if old_field.size() != new_field.size() {
+          changes.push(Change::FieldSizeChanged { location: location.clone(), old: old_field.size(), new: new_field.size() });
+        };
+        if field_kind(old_field) != field_kind(new_field) {
+          changes.push(Change::FieldKindChanged { location: location.clone(), old: field_kind(old_field), new: field_kind(new_field) });
+        };
+        if field_logical_range(old_field) != field_logical_range(new_field) {
+          changes.push(Change::LogicalRangeChanged { location: location.clone(), old: field_logical_range(old_field), new: field_logical_range(new_field) });
+        };
+        if let (ReportField::Variable { physical_minimum: old_minimum, physical_maximum: old_maximum, .. }, ReportField::Variable { physical_minimum: new_minimum, physical_maximum: new_maximum, .. }) = (old_field, new_field) && (old_minimum, old_maximum) != (new_minimum, new_maximum) {
+          changes.push(Change::PhysicalRangeChanged { location: location.clone(), old: (*old_minimum, *old_maximum), new: (*new_minimum, *new_maximum) });
+        };
+        if field_usages(old_field) != field_usages(new_field) {
+          changes.push(Change::UsagesChanged { location: location.clone(), old: field_usages(old_field), new: field_usages(new_field) });
+        };
+        if field_flags(old_field) != field_flags(new_field) {
+          changes.push(Change::FlagsChanged { location, old: field_flags(old_field), new: field_flags(new_field) });
+        };
+      },
+      (None, None) => {},
+    };
+  };
+}
+fn collections(items: &[MainItem]) -> Vec<&Collection> {
+  items.iter().filter_map(|item| match item {
+    MainItem::Collection(collection) => Some(collection),
+    MainItem::Report(_) => None,
+  }).collect()
+}
+fn diff_collections(changes: &mut Vec<Change>, path: &mut Vec<(CollectionType, Option<u32>)>, old: &[MainItem], new: &[MainItem]) {
+  let (old, new) = (collections(old), collections(new));
+  for pair in align(&old, &new, |collection| (collection.ty, collection.usage)) {
+    match pair {
+      (Some(index), None) => {
+        path.push((old[index].ty, old[index].usage));
+        changes.push(Change::CollectionRemoved { path: path.clone() });
+        path.pop();
+      },
+      (None, Some(index)) => {
+        path.push((new[index].ty, new[index].usage));
+        changes.push(Change::CollectionAdded { path: path.clone() });
+        path.pop();
+      },
+      (Some(old_index), Some(new_index)) => {
+        let (old, new) = (old[old_index], new[new_index]);
+        if (old.ty, old.usage) != (new.ty, new.usage) {
+          changes.push(Change::CollectionChanged { path: path.clone(), old: (old.ty, old.usage), new: (new.ty, new.usage) });
+        };
+        path.push((new.ty, new.usage));
+        diff_collections(changes, path, &old.items, &new.items);
+        path.pop();
+      },
+      (None, None) => {},
+    };
+  };
+}
+pub fn diff(old: &Descriptor, new: &Descriptor) -> DescriptorDiff {
+  let mut changes = vec![];
+  let (old_reports, new_reports) = (old.reports(), new.reports());
+  for old_report in &old_reports {
+    match new_reports.iter().find(|report| report.ty == old_report.ty && report.id == old_report.id) {
+      None => changes.push(Change::ReportRemoved { ty: old_report.ty, id: old_report.id, bit_length: old_report.layout().bit_length }),
+      Some(new_report) => {
+        let (old_length, new_length) = (old_report.layout().bit_length, new_report.layout().bit_length);
+        if old_length != new_length {
+          changes.push(Change::ReportLengthChanged { ty: old_report.ty, id: old_report.id, old: old_length, new: new_length });
+        };
+        diff_fields(&mut changes, old_report, new_report);
+      },
+    };
+  };
+  for new_report in &new_reports {
+    if !old_reports.iter().any(|report| report.ty == new_report.ty && report.id == new_report.id) {
+      changes.push(Change::ReportAdded { ty: new_report.ty, id: new_report.id, bit_length: new_report.layout().bit_length });
+    };
+  };
+  diff_collections(&mut changes, &mut vec![], &old.items, &new.items);
+  DescriptorDiff { changes }
+}
+pub fn diff_items(old: Vec<DescriptorItem>, new: Vec<DescriptorItem>) -> Result<DescriptorDiff> {
+  Ok(diff(&Descriptor::try_from_descriptor_items(old)?, &Descriptor::try_from_descriptor_items(new)?))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const MOUSE: [u8; 45] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x05, 0x09,
+    0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01,
+    0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01,
+    0x75, 0x05, 0x81, 0x03, 0x05, 0x01, 0x09, 0x30,
+    0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08,
+    0x95, 0x02, 0x81, 0x06, 0xC0,
+  ];
+  fn mouse() -> Descriptor {
+    Descriptor::try_from_bytes(&MOUSE).unwrap()
+  }
+  #[test]
+  fn equivalent_descriptors_have_no_changes() {
+    let descriptor = mouse();
+    assert!(diff(&descriptor, &descriptor).is_empty());
+    let items = descriptor.clone().into_descriptor_items();
+    let expanded = items_from_bytes(&MOUSE).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(diff_items(items, expanded), Ok(DescriptorDiff::default()));
+  }
+  #[test]
+  fn reports_added_fields() {
+    let mut data = MOUSE[..44].to_vec();
+    data.extend([0x09, 0x38, 0x95, 0x01, 0x81, 0x06, 0xC0]);
+    let changes = diff(&mouse(), &Descriptor::try_from_bytes(&data).unwrap()).changes;
+    assert_eq!(changes, [
+      Change::ReportLengthChanged { ty: ReportType::Input, id: None, old: 24, new: 32 },
+      Change::FieldAdded { location: FieldLocation { ty: ReportType::Input, id: None, field: 6, usage: Some(0x0001_0038) }, bit_offset: 24 },
+    ]);
+  }
+  #[test]
+  fn reports_changed_fields() {
+    let mut data = MOUSE.to_vec();
+    data[37] = 0x7E;
+    let diff = diff(&mouse(), &Descriptor::try_from_bytes(&data).unwrap());
+    assert_eq!(diff.changes.len(), 2);
+    assert_eq!(diff.changes[0], Change::LogicalRangeChanged { location: FieldLocation { ty: ReportType::Input, id: None, field: 4, usage: Some(0x0001_0030) }, old: (-127, 127), new: (-127, 126) });
+    assert!(diff.to_string().lines().all(|line| line.starts_with("~ Unnumbered Input report field ")));
+  }
+  #[test]
+  fn reports_removed_reports_and_collections() {
+    let empty = Descriptor { items: vec![] };
+    let changes = diff(&mouse(), &empty).changes;
+    assert_eq!(changes, [
+      Change::ReportRemoved { ty: ReportType::Input, id: None, bit_length: 24 },
+      Change::CollectionRemoved { path: vec![(CollectionType::Application, Some(0x0001_0002))] },
+    ]);
+    assert_eq!(changes[0].to_string(), "- Unnumbered Input report (24 bits)");
+  }
+  #[test]
+  fn aligns_large_reports() {
+    let old = [0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x09, 0x02, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x96, 0x00, 0x10, 0xB2, 0x02, 0x01, 0xC0];
+    let mut new = old[..7].to_vec();
+    new.extend([0x09, 0x03, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0xB1, 0x02]);
+    new.extend(old[7..].iter().copied());
+    let changes = diff(&Descriptor::try_from_bytes(&old).unwrap(), &Descriptor::try_from_bytes(&new).unwrap()).changes;
+    assert_eq!(changes.len(), 4105);
+    assert_eq!(changes[0], Change::ReportLengthChanged { ty: ReportType::Feature, id: None, old: 32768, new: 32776 });
+    assert_eq!(changes[1], Change::FieldAdded { location: FieldLocation { ty: ReportType::Feature, id: None, field: 0, usage: Some(0xFF00_0003) }, bit_offset: 0 });
+    assert_eq!(changes[9], Change::FieldMoved { location: FieldLocation { ty: ReportType::Feature, id: None, field: 8, usage: Some(0xFF00_0002) }, old: 0, new: 8 });
+    assert_eq!(changes[4104], Change::FieldMoved { location: FieldLocation { ty: ReportType::Feature, id: None, field: 4103, usage: Some(0xFF00_0002) }, old: 32760, new: 32768 });
+  }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use hut::{Usage, UsagePage};
+use crate::descriptor_items::*;
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub(crate) struct AnnotationState {
+  usage_page: Option<u16>,
+  stack: Vec<Option<u16>>,
+}
+pub(crate) fn usage_page_name(page: u16) -> String {
+  match UsagePage::try_from(page) {
+    Ok(page) => page.name(),
+    Err(_) => format!("{page:#06x}"),
+  }
+}
+pub(crate) fn usage_name(usage: u32) -> String {
+  match Usage::try_from(usage) {
+    Ok(usage) => usage.name(),
+    Err(_) => format!("{:#06x}", usage & 0xFFFF),
+  }
+}
+fn usage_specifier_name(usage: UsageSpecifier, state: &AnnotationState) -> String {
+  match usage {
+    UsageSpecifier::Usage(id) => match state.usage_page {
+      Some(page) => usage_name(((page as u32) << 16) | id as u32),
+      None => format!("{id:#06x}"),
+    },
+    UsageSpecifier::ExtendedUsage(usage) => format!("{}: {}", usage_page_name((usage >> 16) as u16), usage_name(usage)),
+  }
+}
+pub(crate) fn collection_type_name(ty: CollectionType) -> String {
+  match ty {
+    CollectionType::Physical => "Physical".to_string(),
+    CollectionType::Application => "Application".to_string(),
+    CollectionType::Logical => "Logical".to_string(),
+    CollectionType::Report => "Report".to_string(),
+    CollectionType::NamedArray => "Named Array".to_string(),
+    CollectionType::UsageSwitch => "Usage Switch".to_string(),
+    CollectionType::UsageModifier => "Usage Modifier".to_string(),
+    CollectionType::VendorDefined(n) => format!("Vendor Defined {n:#04x}"),
+  }
+}
+fn data_bytes(data: &[u8]) -> String {
+  data.iter().map(|byte| format!(" {byte:02x}")).collect::<String>()
+}
+pub(crate) fn item_type_name(kind: ItemType) -> &'static str {
+  match kind {
+    ItemType::Main => "Main",
+    ItemType::Global => "Global",
+    ItemType::Local => "Local",
+    ItemType::Reserved => "Reserved",
+  }
+}
+#[allow(clippy::too_many_arguments)]
+fn main_item_flags(constant: ReportConstantFlag, layout: ReportLayoutFlag, relative: ReportRelativeFlag, wrap: ReportWrapFlag, linear: ReportLinearFlag, preferred_state: ReportPreferredStateFlag, null_state: ReportNullStateFlag, volatile: Option<ReportVolatileFlag>, buffered_bytes: ReportBufferedBytesFlag) -> String {
+  let mut flags = vec![
+    if constant == ReportConstantFlag::Constant { "Const" } else { "Data" },
+    if layout == ReportLayoutFlag::Variable { "Var" } else { "Array" },
+    if relative == ReportRelativeFlag::Relative { "Rel" } else { "Abs" },
+  ];
+  if wrap == ReportWrapFlag::Wrap {
+    flags.push("Wrap");
+  };
+  if linear == ReportLinearFlag::NonLinear {
+    flags.push("NonLin");
+  };
+  if preferred_state == ReportPreferredStateFlag::NoPreferred {
+    flags.push("NoPref");
+  };
+  if null_state == ReportNullStateFlag::NullState {
+    flags.push("Null");
+  };
+  if volatile == Some(ReportVolatileFlag::Volatile) {
+    flags.push("Vol");
+  };
+  if buffered_bytes == ReportBufferedBytesFlag::BufferedBytes {
+    flags.push("Buf");
+  };
+  flags.join(", ")
+}
+pub(crate) fn annotate(item: &DescriptorItem, state: &mut AnnotationState) -> String {
+  match *item {
+    DescriptorItem::Input { constant, layout, relative, wrap, linear, preferred_state, null_state, buffered_bytes } => format!("Input ({})", main_item_flags(constant, layout, relative, wrap, linear, preferred_state, null_state, None, buffered_bytes)),
+    DescriptorItem::Output { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes } => format!("Output ({})", main_item_flags(constant, layout, relative, wrap, linear, preferred_state, null_state, Some(volatile), buffered_bytes)),
+    DescriptorItem::Feature { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes } => format!("Feature ({})", main_item_flags(constant, layout, relative, wrap, linear, preferred_state, null_state, Some(volatile), buffered_bytes)),
+    DescriptorItem::Collection(ty) => format!("Collection ({})", collection_type_name(ty)),
+    DescriptorItem::EndCollection => "End Collection".to_string(),
+    DescriptorItem::UsagePage(page) => {
+      state.usage_page = Some(page);
+      format!("Usage Page ({})", usage_page_name(page))
+    },
+    DescriptorItem::LogicalMinimum(minimum) => format!("Logical Minimum ({minimum})"),
+    DescriptorItem::LogicalMaximum(maximum) => format!("Logical Maximum ({maximum})"),
+    DescriptorItem::PhysicalMinimum(minimum) => format!("Physical Minimum ({minimum})"),
+    DescriptorItem::PhysicalMaximum(maximum) => format!("Physical Maximum ({maximum})"),
+    DescriptorItem::UnitExponent(exponent) => format!("Unit Exponent ({exponent})"),
+    DescriptorItem::Unit(unit) => match unit.symbol() {
+      Some(symbol) => format!("Unit ({}: {symbol})", unit.system().name()),
+      None => format!("Unit ({:#x})", unit.raw()),
+    },
+    DescriptorItem::ReportSize(size) => format!("Report Size ({size})"),
+    DescriptorItem::ReportId(id) => format!("Report ID ({id})"),
+    DescriptorItem::ReportCount(count) => format!("Report Count ({count})"),
+    DescriptorItem::Push => {
+      state.stack.push(state.usage_page);
+      "Push".to_string()
+    },
+    DescriptorItem::Pop => {
+      if let Some(usage_page) = state.stack.pop() {
+        state.usage_page = usage_page;
+      };
+      "Pop".to_string()
+    },
+    DescriptorItem::Usage(usage) => format!("Usage ({})", usage_specifier_name(usage, state)),
+    DescriptorItem::UsageMinimum(usage) => format!("Usage Minimum ({})", usage_specifier_name(usage, state)),
+    DescriptorItem::UsageMaximum(usage) => format!("Usage Maximum ({})", usage_specifier_name(usage, state)),
+    DescriptorItem::DesignatorIndex(index) => format!("Designator Index ({index})"),
+    DescriptorItem::DesignatorMinimum(minimum) => format!("Designator Minimum ({minimum})"),
+    DescriptorItem::DesignatorMaximum(maximum) => format!("Designator Maximum ({maximum})"),
+    DescriptorItem::StringIndex(index) => format!("String Index ({index})"),
+    DescriptorItem::StringMinimum(minimum) => format!("String Minimum ({minimum})"),
+    DescriptorItem::StringMaximum(maximum) => format!("String Maximum ({maximum})"),
+    DescriptorItem::Delimiter(DelimiterFlag::Open) => "Delimiter (Open)".to_string(),
+    DescriptorItem::Delimiter(DelimiterFlag::Close) => "Delimiter (Close)".to_string(),
+    DescriptorItem::Long { tag, ref data } => format!("Long Item ({tag:#04x}{})", data_bytes(data)),
+    DescriptorItem::Reserved { kind, tag, ref data } => format!("Reserved {} Item ({tag:#04x}{})", item_type_name(kind), data_bytes(data)),
+  }
+}
+enum DisplaySource<'a> {
+  Items(&'a [DescriptorItem]),
+  EncodedItems(&'a [EncodedItem]),
+}
+pub struct ItemsDisplay<'a> {
+  source: DisplaySource<'a>,
+}
+pub fn display_items(items: &[DescriptorItem]) -> ItemsDisplay<'_> {
+  ItemsDisplay { source: DisplaySource::Items(items) }
+}
+pub fn display_encoded_items(items: &[EncodedItem]) -> ItemsDisplay<'_> {
+  ItemsDisplay { source: DisplaySource::EncodedItems(items) }
+}
+impl fmt::Display for ItemsDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let lines: Vec<(&DescriptorItem, Vec<u8>)> = match self.source {
+      DisplaySource::Items(items) => items.iter().map(|item| {
+        let mut bytes = vec![];
+        let _ = item.try_write_into(&mut bytes);
+        (item, bytes)
+      }).collect(),
+      DisplaySource::EncodedItems(items) => items.iter().map(|item| {
+        let mut bytes = vec![];
+        let _ = item.try_write_into(&mut bytes, ItemEncoding::Original);
+        (&item.item, bytes)
+      }).collect(),
+    };
+    let mut state = AnnotationState::default();
+    let mut depth: usize = 0;
+    for (item, bytes) in lines {
+      if *item == DescriptorItem::EndCollection {
+        depth = depth.saturating_sub(1);
+      };
+      let hex = bytes.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<String>>().join(" ");
+      writeln!(f, "{hex:<14}  {}{}", "  ".repeat(depth), annotate(item, &mut state))?;
+      if let DescriptorItem::Collection(_) = item {
+        depth += 1;
+      };
+    };
+    Ok(())
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::error::Result;
+  const KEYBOARD: [u8; 33] = [
+    0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x05, 0x07,
+    0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01,
+    0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x55, 0xFE,
+    0x66, 0x11, 0xE1, 0x0B, 0x38, 0x00, 0x01, 0x00,
+    0xC0,
+  ];
+  #[test]
+  fn annotates_items_with_hex_bytes() {
+    let items = items_from_bytes(&KEYBOARD).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(display_items(&items).to_string(), [
+      "05 01           Usage Page (Generic Desktop)",
+      "09 06           Usage (Keyboard)",
+      "a1 01           Collection (Application)",
+      "05 07             Usage Page (Keyboard/Keypad)",
+      "19 e0             Usage Minimum (Keyboard LeftControl)",
+      "29 e7             Usage Maximum (Keyboard Right GUI)",
+      "14                Logical Minimum (0)",
+      "25 01             Logical Maximum (1)",
+      "75 01             Report Size (1)",
+      "95 08             Report Count (8)",
+      "81 02             Input (Data, Var, Abs)",
+      "55 fe             Unit Exponent (-2)",
+      "66 11 e1          Unit (SI Linear: cm·g·s⁻²)",
+      "0b 38 00 01 00    Usage (Generic Desktop: Wheel)",
+      "c0              End Collection",
+      "",
+    ].join("\n"));
+  }
+  #[test]
+  fn annotates_original_bytes() {
+    let items = encoded_items_from_bytes(&KEYBOARD).collect::<Result<Vec<_>>>().unwrap();
+    let text = display_encoded_items(&items).to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[6], "15 00             Logical Minimum (0)");
+    assert_eq!(lines[11], "55 fe             Unit Exponent (-2)");
+  }
+  #[test]
+  fn restores_usage_pages_on_pop() {
+    let items = [DescriptorItem::UsagePage(0x01), DescriptorItem::Push, DescriptorItem::UsagePage(0x09), DescriptorItem::Usage(UsageSpecifier::Usage(0x01)), DescriptorItem::Pop, DescriptorItem::Usage(UsageSpecifier::Usage(0x30))];
+    let text = display_items(&items).to_string();
+    assert!(text.lines().map(|line| &line[16..]).eq(["Usage Page (Generic Desktop)", "Push", "Usage Page (Button)", "Usage (Button 1)", "Pop", "Usage (X)"]));
+  }
+}
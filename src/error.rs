@@ -0,0 +1,67 @@
+use std::fmt;
+use crate::descriptor_items::ItemType;
+use crate::descriptors::{MAX_REPORT_LENGTH, ReportType};
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Error {
+  UnexpectedEnd { offset: usize },
+  InvalidItemLength { offset: usize },
+  UnencodableItemData { length: usize },
+  InvalidReservedTag { kind: ItemType, tag: u8 },
+  InvalidCollectionType { offset: usize, tag: u8, value: u32 },
+  InvalidUnitExponent { offset: usize, value: i32 },
+  UndefinedGlobal { index: usize, name: &'static str },
+  LimitExceeded { index: usize, name: &'static str },
+  UnmatchedEndCollection { index: usize },
+  UnclosedCollection { index: usize },
+  UnmatchedPop { index: usize },
+  BufferTooSmall { expected: usize, found: usize },
+  InvalidSyntax { line: usize },
+  UnknownItemName { line: usize },
+  InvalidArgument { line: usize },
+  ValueCountMismatch { expected: usize, found: usize },
+  ValueTypeMismatch { field: usize },
+  ValueOutOfRange { field: usize },
+  UsageOutOfRange { field: usize },
+  NoNullValue { field: usize },
+  UnsupportedFieldSize { field: usize, size: u32 },
+  ReportIdMismatch { expected: u8, found: u8 },
+  ReportTooShort { expected: usize, found: usize },
+  UnknownReport { ty: ReportType, id: Option<u8> },
+  InvalidUnit { unit: String },
+  UnitExponentOutOfRange { exponent: i8 },
+}
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::UnexpectedEnd { offset } => write!(f, "Descriptor ends in the middle of the item at byte {offset}"),
+      Error::InvalidItemLength { offset } => write!(f, "The size field of the item at byte {offset} does not match the length of the data"),
+      Error::UnencodableItemData { length } => write!(f, "Item data of {length} bytes cannot be encoded"),
+      Error::InvalidReservedTag { kind, tag } => write!(f, "Tag {tag:#x} is not a reserved {kind:?} item tag"),
+      Error::InvalidCollectionType { offset, tag, value } => write!(f, "Invalid collection type {value:#x} in item {tag:#04x} at byte {offset}"),
+      Error::InvalidUnitExponent { offset, value } => write!(f, "Unit exponent {value} of the item at byte {offset} is outside the range -8 to 15"),
+      Error::UndefinedGlobal { index, name } => write!(f, "{name} is not defined for the main item at index {index}"),
+      Error::LimitExceeded { index, name } => write!(f, "{name} of the item at index {index} exceeds the maximum report length of {MAX_REPORT_LENGTH} bytes"),
+      Error::UnmatchedEndCollection { index } => write!(f, "End Collection at index {index} without a matching Collection"),
+      Error::UnclosedCollection { index } => write!(f, "Collection at index {index} without a matching End Collection"),
+      Error::UnmatchedPop { index } => write!(f, "Pop at index {index} without a matching Push"),
+      Error::BufferTooSmall { expected, found } => write!(f, "Expected a buffer of at least {expected} bytes but found {found}"),
+      Error::InvalidSyntax { line } => write!(f, "Invalid syntax on line {line}"),
+      Error::UnknownItemName { line } => write!(f, "Unknown item name on line {line}"),
+      Error::InvalidArgument { line } => write!(f, "Invalid item argument on line {line}"),
+      Error::ValueCountMismatch { expected, found } => write!(f, "Expected {expected} values but {found} were provided"),
+      Error::ValueTypeMismatch { field } => write!(f, "Value does not match report field {field}"),
+      Error::ValueOutOfRange { field } => write!(f, "Value of report field {field} is out of the specified range"),
+      Error::UsageOutOfRange { field } => write!(f, "Usage of report field {field} is out of the specified range"),
+      Error::NoNullValue { field } => write!(f, "The logical minimum and logical maximum of report field {field} contain all possible values"),
+      Error::UnsupportedFieldSize { field, size } => write!(f, "Report field {field} has an unsupported size of {size} bits"),
+      Error::ReportIdMismatch { expected, found } => write!(f, "Expected report ID {expected} but found {found}"),
+      Error::ReportTooShort { expected, found } => write!(f, "Expected a report of at least {expected} bytes but found {found}"),
+      Error::UnknownReport { ty, id: Some(id) } => write!(f, "No {ty:?} report with ID {id}"),
+      Error::UnknownReport { ty, id: None } => write!(f, "No unnumbered {ty:?} report"),
+      Error::InvalidUnit { unit } => write!(f, "Invalid unit {unit}"),
+      Error::UnitExponentOutOfRange { exponent } => write!(f, "Unit exponent {exponent} is outside the range -8 to 7"),
+    }
+  }
+}
+impl std::error::Error for Error {}
+pub type Result<T> = std::result::Result<T, Error>;
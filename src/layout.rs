@@ -0,0 +1,122 @@
+use crate::descriptors::*;
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FieldLayout {
+  pub field: usize,
+  pub bit_offset: usize,
+  pub bit_size: u32,
+  pub count: u32,
+}
+impl FieldLayout {
+  pub fn bit_length(&self) -> usize {
+    self.bit_size as usize*self.count as usize
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReportLayout {
+  pub ty: ReportType,
+  pub id: Option<u8>,
+  pub bit_length: usize,
+  pub fields: Vec<FieldLayout>,
+}
+impl ReportLayout {
+  pub fn byte_length(&self) -> usize {
+    self.bit_length.div_ceil(8)
+  }
+  pub fn field_bit_offset(&self, field: usize) -> Option<usize> {
+    self.fields.iter().find(|layout| field >= layout.field && field < layout.field+layout.count as usize).map(|layout| layout.bit_offset+(field-layout.field)*layout.bit_size as usize)
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DescriptorLayout {
+  pub reports: Vec<ReportLayout>,
+}
+impl DescriptorLayout {
+  pub fn report(&self, ty: ReportType, id: Option<u8>) -> Option<&ReportLayout> {
+    self.reports.iter().find(|report| report.ty == ty && report.id == id)
+  }
+  pub fn max_length(&self, ty: ReportType) -> usize {
+    self.reports.iter().filter(|report| report.ty == ty).map(ReportLayout::byte_length).max().unwrap_or(0)
+  }
+  pub fn max_input_length(&self) -> usize {
+    self.max_length(ReportType::Input)
+  }
+  pub fn max_output_length(&self) -> usize {
+    self.max_length(ReportType::Output)
+  }
+  pub fn max_feature_length(&self) -> usize {
+    self.max_length(ReportType::Feature)
+  }
+}
+fn same_main_item(previous: &ReportField, field: &ReportField) -> bool {
+  match (previous, field) {
+    (ReportField::Variable { .. }, ReportField::Variable { .. }) => {
+      let strip = |field: &ReportField| match field.clone() {
+        ReportField::Variable { size, logical_minimum, logical_maximum, physical_minimum, physical_maximum, unit_exponent, unit, constant, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes, .. } => ReportField::Variable { size, logical_minimum, logical_maximum, physical_minimum, physical_maximum, unit_exponent, unit, usages: None, designator_index: None, string_index: None, constant, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes },
+        field => field,
+      };
+      strip(previous) == strip(field)
+    },
+    (ReportField::Array { .. }, ReportField::Array { .. }) => previous == field,
+    _ => false,
+  }
+}
+impl Report {
+  pub fn layout(&self) -> ReportLayout {
+    let mut bit_offset: usize = if self.id.is_some() { 8 } else { 0 };
+    let mut fields: Vec<FieldLayout> = vec![];
+    for (index, field) in self.fields.iter().enumerate() {
+      let bit_size = field.size();
+      match fields.last_mut() {
+        Some(layout) if same_main_item(&self.fields[index-1], field) => layout.count += 1,
+        _ => fields.push(FieldLayout { field: index, bit_offset, bit_size, count: 1 }),
+      };
+      bit_offset += bit_size as usize;
+    };
+    ReportLayout { ty: self.ty, id: self.id, bit_length: bit_offset, fields }
+  }
+}
+impl Descriptor {
+  pub fn layout(&self) -> DescriptorLayout {
+    DescriptorLayout { reports: self.reports().iter().map(Report::layout).collect() }
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const NUMBERED_MOUSE: [u8; 58] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x85, 0x01,
+    0x05, 0x09, 0x19, 0x01, 0x29, 0x03, 0x15, 0x00,
+    0x25, 0x01, 0x75, 0x01, 0x95, 0x03, 0x81, 0x02,
+    0x95, 0x01, 0x75, 0x05, 0x81, 0x03, 0x05, 0x01,
+    0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F,
+    0x75, 0x08, 0x95, 0x02, 0x81, 0x06, 0x85, 0x02,
+    0x09, 0x48, 0x26, 0xFF, 0x00, 0x75, 0x10, 0xB1,
+    0x02, 0xC0,
+  ];
+  #[test]
+  fn lays_out_numbered_reports() {
+    let layout = Descriptor::try_from_bytes(&NUMBERED_MOUSE).unwrap().layout();
+    assert_eq!(layout.reports, [
+      ReportLayout { ty: ReportType::Input, id: Some(1), bit_length: 32, fields: vec![
+        FieldLayout { field: 0, bit_offset: 8, bit_size: 1, count: 3 },
+        FieldLayout { field: 3, bit_offset: 11, bit_size: 5, count: 1 },
+        FieldLayout { field: 4, bit_offset: 16, bit_size: 8, count: 2 },
+      ] },
+      ReportLayout { ty: ReportType::Feature, id: Some(2), bit_length: 40, fields: vec![
+        FieldLayout { field: 0, bit_offset: 8, bit_size: 16, count: 2 },
+      ] },
+    ]);
+    let input = layout.report(ReportType::Input, Some(1)).unwrap();
+    assert_eq!(input.byte_length(), 4);
+    assert_eq!(input.fields[2].bit_length(), 16);
+    assert_eq!((input.field_bit_offset(2), input.field_bit_offset(5), input.field_bit_offset(6)), (Some(10), Some(24), None));
+    assert_eq!(layout.report(ReportType::Input, None), None);
+    assert_eq!((layout.max_input_length(), layout.max_output_length(), layout.max_feature_length()), (4, 0, 5));
+  }
+  #[test]
+  fn lays_out_unaligned_unnumbered_reports() {
+    let report = Descriptor::try_from_bytes(&[0x15, 0x00, 0x25, 0x07, 0x75, 0x03, 0x95, 0x03, 0x91, 0x02]).unwrap().reports()[0].layout();
+    assert_eq!(report.fields, [FieldLayout { field: 0, bit_offset: 0, bit_size: 3, count: 3 }]);
+    assert_eq!((report.bit_length, report.byte_length()), (9, 2));
+  }
+}
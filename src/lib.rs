@@ -1,3 +1,16 @@
 pub mod descriptor_items;
 pub mod descriptors;
+pub mod diff;
+pub mod display;
+pub mod error;
+pub mod layout;
+pub mod macros;
+pub mod minimize;
+pub mod normalize;
+pub mod physical;
+pub mod query;
+pub mod report_map;
 pub mod reports;
+pub mod text;
+pub mod units;
+pub mod validate;
@@ -0,0 +1,355 @@
+#![allow(clippy::unusual_byte_groupings)]
+use crate::descriptor_items::*;
+pub use hut;
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MainItemFlag {
+  Data,
+  Const,
+  Constant,
+  Array,
+  Var,
+  Variable,
+  Abs,
+  Absolute,
+  Rel,
+  Relative,
+  NoWrap,
+  Wrap,
+  Lin,
+  Linear,
+  NonLin,
+  NonLinear,
+  Pref,
+  PreferredState,
+  NoPref,
+  NoPreferred,
+  NoNull,
+  NoNullPosition,
+  Null,
+  NullState,
+  NonVol,
+  NonVolatile,
+  Vol,
+  Volatile,
+  BitField,
+  Buf,
+  BufferedBytes,
+}
+const fn main_item(tag: u8, flags: &[MainItemFlag]) -> DescriptorItem {
+  let mut constant = ReportConstantFlag::Data;
+  let mut layout = ReportLayoutFlag::Array;
+  let mut relative = ReportRelativeFlag::Absolute;
+  let mut wrap = ReportWrapFlag::NoWrap;
+  let mut linear = ReportLinearFlag::Linear;
+  let mut preferred_state = ReportPreferredStateFlag::PreferredState;
+  let mut null_state = ReportNullStateFlag::NoNullPosition;
+  let mut volatile = ReportVolatileFlag::NonVolatile;
+  let mut buffered_bytes = ReportBufferedBytesFlag::BitField;
+  let mut index = 0;
+  while index < flags.len() {
+    match flags[index] {
+      MainItemFlag::Data => constant = ReportConstantFlag::Data,
+      MainItemFlag::Const | MainItemFlag::Constant => constant = ReportConstantFlag::Constant,
+      MainItemFlag::Array => layout = ReportLayoutFlag::Array,
+      MainItemFlag::Var | MainItemFlag::Variable => layout = ReportLayoutFlag::Variable,
+      MainItemFlag::Abs | MainItemFlag::Absolute => relative = ReportRelativeFlag::Absolute,
+      MainItemFlag::Rel | MainItemFlag::Relative => relative = ReportRelativeFlag::Relative,
+      MainItemFlag::NoWrap => wrap = ReportWrapFlag::NoWrap,
+      MainItemFlag::Wrap => wrap = ReportWrapFlag::Wrap,
+      MainItemFlag::Lin | MainItemFlag::Linear => linear = ReportLinearFlag::Linear,
+      MainItemFlag::NonLin | MainItemFlag::NonLinear => linear = ReportLinearFlag::NonLinear,
+      MainItemFlag::Pref | MainItemFlag::PreferredState => preferred_state = ReportPreferredStateFlag::PreferredState,
+      MainItemFlag::NoPref | MainItemFlag::NoPreferred => preferred_state = ReportPreferredStateFlag::NoPreferred,
+      MainItemFlag::NoNull | MainItemFlag::NoNullPosition => null_state = ReportNullStateFlag::NoNullPosition,
+      MainItemFlag::Null | MainItemFlag::NullState => null_state = ReportNullStateFlag::NullState,
+      MainItemFlag::NonVol | MainItemFlag::NonVolatile | MainItemFlag::Vol | MainItemFlag::Volatile if tag == 0b1000_00 => panic!("Input items cannot be volatile"),
+      MainItemFlag::NonVol | MainItemFlag::NonVolatile => volatile = ReportVolatileFlag::NonVolatile,
+      MainItemFlag::Vol | MainItemFlag::Volatile => volatile = ReportVolatileFlag::Volatile,
+      MainItemFlag::BitField => buffered_bytes = ReportBufferedBytesFlag::BitField,
+      MainItemFlag::Buf | MainItemFlag::BufferedBytes => buffered_bytes = ReportBufferedBytesFlag::BufferedBytes,
+    };
+    index += 1;
+  };
+  match tag {
+    0b1000_00 => DescriptorItem::Input { constant, layout, relative, wrap, linear, preferred_state, null_state, buffered_bytes },
+    0b1001_00 => DescriptorItem::Output { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes },
+    _ => DescriptorItem::Feature { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile, buffered_bytes },
+  }
+}
+pub const fn input(flags: &[MainItemFlag]) -> DescriptorItem {
+  main_item(0b1000_00, flags)
+}
+pub const fn output(flags: &[MainItemFlag]) -> DescriptorItem {
+  main_item(0b1001_00, flags)
+}
+pub const fn feature(flags: &[MainItemFlag]) -> DescriptorItem {
+  main_item(0b1011_00, flags)
+}
+pub const fn collection(ty: CollectionType) -> DescriptorItem {
+  if let CollectionType::VendorDefined(0..=0x7F) = ty {
+    panic!("Vendor defined collection types must be between 0x80 and 0xFF");
+  };
+  DescriptorItem::Collection(ty)
+}
+pub const fn usage(usage: u32) -> UsageSpecifier {
+  if usage > 0xFFFF {
+    UsageSpecifier::ExtendedUsage(usage)
+  }
+  else {
+    UsageSpecifier::Usage(usage as u16)
+  }
+}
+pub const fn unit_exponent(exponent: i8) -> DescriptorItem {
+  if exponent < -8 || exponent > 7 {
+    panic!("Unit Exponent must be between -8 and 7");
+  };
+  DescriptorItem::UnitExponent(exponent)
+}
+pub const fn report_size(size: u32) -> DescriptorItem {
+  if size == 0 {
+    panic!("Report Size must not be zero");
+  };
+  DescriptorItem::ReportSize(size)
+}
+pub const fn report_id(id: u8) -> DescriptorItem {
+  if id == 0 {
+    panic!("Report ID must not be zero");
+  };
+  DescriptorItem::ReportId(id)
+}
+pub const fn report_count(count: u32) -> DescriptorItem {
+  if count == 0 {
+    panic!("Report Count must not be zero");
+  };
+  DescriptorItem::ReportCount(count)
+}
+#[macro_export]
+macro_rules! hid_descriptor {
+  ($($body:tt)*) => {{
+    let mut items: ::std::vec::Vec<$crate::descriptor_items::DescriptorItem> = ::std::vec::Vec::new();
+    $crate::__hid_descriptor_items!(items [] [] $($body)*);
+    items
+  }};
+}
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __hid_descriptor_items {
+  ($items:ident [$($page:ident)?] [$($stack:tt)*]) => {};
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] , $($rest:tt)*) => {
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] @end_collection $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::EndCollection);
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] @close_delimiter $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::Delimiter($crate::descriptor_items::DelimiterFlag::Close));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] input($($flag:ident),* $(,)?) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::input(&[$($crate::macros::MainItemFlag::$flag),*]) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] output($($flag:ident),* $(,)?) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::output(&[$($crate::macros::MainItemFlag::$flag),*]) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] feature($($flag:ident),* $(,)?) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::feature(&[$($crate::macros::MainItemFlag::$flag),*]) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] collection($($ty:tt)+) { $($inner:tt)* } $($rest:tt)*) => {
+    $items.push(const { $crate::macros::collection($crate::descriptor_items::CollectionType::$($ty)+) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($inner)* @end_collection $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] delimiter { $($inner:tt)* } $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::Delimiter($crate::descriptor_items::DelimiterFlag::Open));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($inner)* @close_delimiter $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] usage_page($name:ident) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::UsagePage($crate::macros::hut::AsUsagePage::usage_page_value(&$crate::macros::hut::UsagePage::$name)));
+    $crate::__hid_descriptor_items!($items [$name] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] usage_page($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::UsagePage($value));
+    $crate::__hid_descriptor_items!($items [] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] logical_minimum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::LogicalMinimum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] logical_maximum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::LogicalMaximum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] physical_minimum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::PhysicalMinimum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] physical_maximum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::PhysicalMaximum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] unit_exponent($value:expr) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::unit_exponent($value) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] unit($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::Unit($crate::units::Unit::from($value)));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] report_size($value:expr) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::report_size($value) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] report_id($value:expr) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::report_id($value) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] report_count($value:expr) $($rest:tt)*) => {
+    $items.push(const { $crate::macros::report_count($value) });
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] push $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::Push);
+    $crate::__hid_descriptor_items!($items [$($page)?] [{$($page)?} $($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [{$($previous:ident)?} $($stack:tt)*] pop $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::Pop);
+    $crate::__hid_descriptor_items!($items [$($previous)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [] pop $($rest:tt)*) => {
+    ::std::compile_error!("Pop without a matching Push");
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] designator_index($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::DesignatorIndex($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] designator_minimum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::DesignatorMinimum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] designator_maximum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::DesignatorMaximum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] string_index($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::StringIndex($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] string_minimum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::StringMinimum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] string_maximum($value:expr) $($rest:tt)*) => {
+    $items.push($crate::descriptor_items::DescriptorItem::StringMaximum($value));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] $kind:ident($usage_page:ident :: $name:ident) $($rest:tt)*) => {
+    $items.push($crate::__hid_usage_item!($kind($crate::descriptor_items::UsageSpecifier::ExtendedUsage($crate::macros::hut::AsUsage::usage_value(&$crate::macros::hut::$usage_page::$name)))));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [$page:ident] [$($stack:tt)*] $kind:ident($name:ident) $($rest:tt)*) => {
+    $items.push($crate::__hid_usage_item!($kind($crate::descriptor_items::UsageSpecifier::Usage($crate::macros::hut::AsUsage::usage_id_value(&$crate::macros::hut::$page::$name)))));
+    $crate::__hid_descriptor_items!($items [$page] [$($stack)*] $($rest)*);
+  };
+  ($items:ident [] [$($stack:tt)*] $kind:ident($name:ident) $($rest:tt)*) => {
+    ::std::compile_error!(::std::concat!("Named usage `", ::std::stringify!($name), "` requires a named Usage Page"));
+  };
+  ($items:ident [$($page:ident)?] [$($stack:tt)*] $kind:ident($value:expr) $($rest:tt)*) => {
+    $items.push($crate::__hid_usage_item!($kind(const { $crate::macros::usage($value) })));
+    $crate::__hid_descriptor_items!($items [$($page)?] [$($stack)*] $($rest)*);
+  };
+}
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __hid_usage_item {
+  (usage($usage:expr)) => {
+    $crate::descriptor_items::DescriptorItem::Usage($usage)
+  };
+  (usage_minimum($usage:expr)) => {
+    $crate::descriptor_items::DescriptorItem::UsageMinimum($usage)
+  };
+  (usage_maximum($usage:expr)) => {
+    $crate::descriptor_items::DescriptorItem::UsageMaximum($usage)
+  };
+  ($kind:ident($usage:expr)) => {
+    ::std::compile_error!(::std::concat!("Unknown descriptor item `", ::std::stringify!($kind), "`"))
+  };
+}
+#[cfg(test)]
+mod tests {
+  use crate::descriptor_items::*;
+  use crate::units::Unit;
+  const SIZE: u32 = 8;
+  const COUNT: u32 = 2;
+  const ID: u8 = 3;
+  const MINIMUM: i32 = -127;
+  const MAXIMUM: i32 = 127;
+  const EXPONENT: i8 = -2;
+  const INDEX: u32 = 4;
+  const PAGE: u16 = 0xFF00;
+  const VENDOR_USAGE: u32 = 0x01;
+  #[test]
+  fn accepts_constant_arguments() {
+    let items = crate::hid_descriptor! {
+      usage_page(GenericDesktop)
+      usage(Mouse)
+      collection(Application) {
+        report_id(ID)
+        usage(X)
+        usage(GenericDesktop::Y)
+        logical_minimum(MINIMUM)
+        logical_maximum(MAXIMUM)
+        physical_minimum(MINIMUM)
+        physical_maximum(MAXIMUM)
+        unit_exponent(EXPONENT)
+        unit(Unit::NONE)
+        report_size(SIZE)
+        report_count(COUNT)
+        designator_index(INDEX)
+        string_index(INDEX)
+        input(Data, Var, Rel)
+      }
+    };
+    assert_eq!(items, [
+      DescriptorItem::UsagePage(0x01),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0x02)),
+      DescriptorItem::Collection(CollectionType::Application),
+      DescriptorItem::ReportId(3),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0x30)),
+      DescriptorItem::Usage(UsageSpecifier::ExtendedUsage(0x0001_0031)),
+      DescriptorItem::LogicalMinimum(-127),
+      DescriptorItem::LogicalMaximum(127),
+      DescriptorItem::PhysicalMinimum(-127),
+      DescriptorItem::PhysicalMaximum(127),
+      DescriptorItem::UnitExponent(-2),
+      DescriptorItem::Unit(Unit::NONE),
+      DescriptorItem::ReportSize(8),
+      DescriptorItem::ReportCount(2),
+      DescriptorItem::DesignatorIndex(4),
+      DescriptorItem::StringIndex(4),
+      crate::macros::input(&[crate::macros::MainItemFlag::Data, crate::macros::MainItemFlag::Var, crate::macros::MainItemFlag::Rel]),
+      DescriptorItem::EndCollection,
+    ]);
+  }
+  #[test]
+  fn accepts_constant_usages_in_braces() {
+    let items = crate::hid_descriptor! {
+      usage_page({PAGE})
+      usage({VENDOR_USAGE})
+      usage_minimum({VENDOR_USAGE+1})
+      usage_maximum({0x0001_0030})
+      usage_page(Button)
+      usage({VENDOR_USAGE})
+    };
+    assert_eq!(items, [
+      DescriptorItem::UsagePage(0xFF00),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0x01)),
+      DescriptorItem::UsageMinimum(UsageSpecifier::Usage(0x02)),
+      DescriptorItem::UsageMaximum(UsageSpecifier::ExtendedUsage(0x0001_0030)),
+      DescriptorItem::UsagePage(0x09),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0x01)),
+    ]);
+  }
+}
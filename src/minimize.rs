@@ -0,0 +1,185 @@
+use crate::descriptor_items::*;
+use crate::descriptors::*;
+use crate::error::Result;
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Minimized {
+  pub items: Vec<DescriptorItem>,
+  pub original_length: usize,
+  pub length: usize,
+}
+impl Minimized {
+  pub fn bytes_saved(&self) -> usize {
+    self.original_length.saturating_sub(self.length)
+  }
+}
+fn encoded_length(items: &[DescriptorItem]) -> Result<usize> {
+  Ok(try_items_into_bytes(items)?.len())
+}
+fn equivalent(items: &[DescriptorItem], descriptor: &Descriptor) -> bool {
+  Descriptor::try_from_descriptor_items(items.to_vec()).is_ok_and(|parsed| parsed == *descriptor)
+}
+fn is_removable(item: &DescriptorItem) -> bool {
+  !matches!(item, DescriptorItem::Input { .. } | DescriptorItem::Output { .. } | DescriptorItem::Feature { .. } | DescriptorItem::Collection(_) | DescriptorItem::EndCollection | DescriptorItem::Long { .. } | DescriptorItem::Reserved { .. })
+}
+fn remove_items(items: &mut Vec<DescriptorItem>, descriptor: &Descriptor, filter: fn(&DescriptorItem) -> bool) -> bool {
+  let mut changed = false;
+  for index in (0..items.len()).rev() {
+    if !filter(&items[index]) {
+      continue;
+    };
+    let item = items.remove(index);
+    if equivalent(items, descriptor) {
+      changed = true;
+    }
+    else {
+      items.insert(index, item);
+    };
+  };
+  changed
+}
+fn usage_pages(items: &[DescriptorItem]) -> Vec<Option<u16>> {
+  let mut pages = Vec::with_capacity(items.len());
+  let mut page = None;
+  let mut stack = vec![];
+  for item in items {
+    match *item {
+      DescriptorItem::UsagePage(value) => page = Some(value),
+      DescriptorItem::Push => stack.push(page),
+      DescriptorItem::Pop => page = stack.pop().unwrap_or(page),
+      _ => {},
+    };
+    pages.push(page);
+  };
+  pages
+}
+fn shorten_usages(items: &mut Vec<DescriptorItem>, descriptor: &Descriptor) -> Result<bool> {
+  let mut changed = false;
+  let mut index = 0;
+  while index < items.len() {
+    let page = usage_pages(items)[index];
+    let replacement = match items[index] {
+      DescriptorItem::Usage(UsageSpecifier::ExtendedUsage(usage)) if page == Some((usage >> 16) as u16) => Some(DescriptorItem::Usage(UsageSpecifier::Usage(usage as u16))),
+      DescriptorItem::UsageMinimum(UsageSpecifier::ExtendedUsage(usage)) if page == Some((usage >> 16) as u16) => Some(DescriptorItem::UsageMinimum(UsageSpecifier::Usage(usage as u16))),
+      DescriptorItem::UsageMaximum(UsageSpecifier::ExtendedUsage(usage)) if page == Some((usage >> 16) as u16) => Some(DescriptorItem::UsageMaximum(UsageSpecifier::Usage(usage as u16))),
+      _ => None,
+    };
+    if let Some(replacement) = replacement {
+      let item = std::mem::replace(&mut items[index], replacement);
+      if equivalent(items, descriptor) {
+        changed = true;
+      }
+      else {
+        items[index] = item;
+      };
+    }
+    else if let (DescriptorItem::Usage(UsageSpecifier::Usage(usage)), Some(page)) = (&items[index], page) {
+      let mut candidate = items.clone();
+      candidate[index] = DescriptorItem::Usage(UsageSpecifier::ExtendedUsage(((page as u32) << 16) | *usage as u32));
+      remove_items(&mut candidate, descriptor, |item| matches!(item, DescriptorItem::UsagePage(_)));
+      if encoded_length(&candidate)? < encoded_length(items)? && equivalent(&candidate, descriptor) {
+        *items = candidate;
+        changed = true;
+      };
+    };
+    index += 1;
+  };
+  Ok(changed)
+}
+fn merge_usage_ranges(items: &mut Vec<DescriptorItem>, descriptor: &Descriptor) -> bool {
+  let mut changed = false;
+  let mut start = 0;
+  while start < items.len() {
+    let DescriptorItem::Usage(UsageSpecifier::Usage(first)) = items[start] else {
+      start += 1;
+      continue;
+    };
+    let mut end = start+1;
+    while let Some(DescriptorItem::Usage(UsageSpecifier::Usage(usage))) = items.get(end) && *usage as usize == first as usize+end-start {
+      end += 1;
+    };
+    if end-start > 2 {
+      let mut candidate = items.clone();
+      let last = first+(end-start-1) as u16;
+      candidate.splice(start..end, [DescriptorItem::UsageMinimum(UsageSpecifier::Usage(first)), DescriptorItem::UsageMaximum(UsageSpecifier::Usage(last))]);
+      if equivalent(&candidate, descriptor) {
+        *items = candidate;
+        changed = true;
+      };
+    };
+    start += 1;
+  };
+  changed
+}
+pub fn minimize(items: Vec<DescriptorItem>) -> Result<Minimized> {
+  let original_length = encoded_length(&items)?;
+  minimize_from(items, original_length)
+}
+fn minimize_from(items: Vec<DescriptorItem>, original_length: usize) -> Result<Minimized> {
+  let descriptor = Descriptor::try_from_descriptor_items(items.clone())?;
+  let mut items = items;
+  let opaque = items.iter().any(|item| matches!(item, DescriptorItem::Long { .. } | DescriptorItem::Reserved { .. }));
+  if !opaque {
+    let regenerated = descriptor.clone().try_into_descriptor_items_with(GlobalStacking::WhenShorter)?;
+    if encoded_length(&regenerated)? < encoded_length(&items)? && equivalent(&regenerated, &descriptor) {
+      items = regenerated;
+    };
+  };
+  loop {
+    let mut changed = remove_items(&mut items, &descriptor, is_removable);
+    changed |= merge_usage_ranges(&mut items, &descriptor);
+    changed |= shorten_usages(&mut items, &descriptor)?;
+    if !changed {
+      break;
+    };
+  };
+  let length = encoded_length(&items)?;
+  Ok(Minimized { items, original_length, length })
+}
+pub fn minimize_bytes(data: &[u8]) -> Result<(Vec<u8>, usize)> {
+  let items = items_from_bytes(data).collect::<Result<Vec<_>>>()?;
+  let minimized = minimize_from(items, data.len())?;
+  let saved = minimized.bytes_saved();
+  Ok((try_items_into_bytes(&minimized.items)?, saved))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::error::Error;
+  const MOUSE: [u8; 59] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01,
+    0xA1, 0x00, 0x05, 0x09, 0x09, 0x01, 0x09, 0x02,
+    0x09, 0x03, 0x15, 0x00, 0x25, 0x01, 0x95, 0x03,
+    0x75, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x05,
+    0x81, 0x03, 0x05, 0x01, 0x09, 0x30, 0x09, 0x31,
+    0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x02,
+    0x81, 0x06, 0x15, 0x81, 0x25, 0x7F, 0x26, 0x7F,
+    0x00, 0xC0, 0xC0,
+  ];
+  #[test]
+  fn minimizes_to_an_equivalent_descriptor() {
+    let (bytes, saved) = minimize_bytes(&MOUSE).unwrap();
+    assert_eq!(Descriptor::try_from_bytes(&bytes), Descriptor::try_from_bytes(&MOUSE));
+    assert_eq!(saved, MOUSE.len()-bytes.len());
+    assert!(bytes.len() < 50);
+    let minimized = minimize(items_from_bytes(&bytes).collect::<Result<Vec<_>>>().unwrap()).unwrap();
+    assert_eq!(minimized.bytes_saved(), 0);
+    assert_eq!(items_into_bytes(&minimized.items), bytes);
+  }
+  #[test]
+  fn measures_savings_against_the_input() {
+    let data = [0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0xC0];
+    let padded = [0x05, 0x01, 0x0A, 0x02, 0x00, 0xA1, 0x01, 0xC0];
+    assert_eq!(minimize_bytes(&data), Ok((data.to_vec(), 0)));
+    assert_eq!(minimize_bytes(&padded), Ok((data.to_vec(), 1)));
+    let minimized = Minimized { items: vec![], original_length: 1, length: 2 };
+    assert_eq!(minimized.bytes_saved(), 0);
+  }
+  #[test]
+  fn rejects_invalid_unit_exponents() {
+    let data = [0x55, 0x10, 0x15, 0x00, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02];
+    assert_eq!(minimize_bytes(&data), Err(Error::InvalidUnitExponent { offset: 0, value: 16 }));
+    let mut items = items_from_bytes(&data[2..]).collect::<Result<Vec<_>>>().unwrap();
+    items.insert(0, DescriptorItem::UnitExponent(16));
+    assert_eq!(minimize(items), Err(Error::UnitExponentOutOfRange { exponent: 16 }));
+  }
+}
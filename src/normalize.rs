@@ -0,0 +1,111 @@
+use crate::descriptor_items::*;
+use crate::descriptors::*;
+use crate::error::Result;
+use crate::units::Unit;
+fn normalize_field(field: &mut ReportField) {
+  if let ReportField::Variable { physical_minimum, physical_maximum, unit_exponent, unit, .. } = field {
+    if physical_minimum.unwrap_or(0) == 0 && physical_maximum.unwrap_or(0) == 0 {
+      *physical_minimum = None;
+      *physical_maximum = None;
+    };
+    if *unit_exponent == Some(0) {
+      *unit_exponent = None;
+    };
+    if *unit == Some(Unit::NONE) {
+      *unit = None;
+    };
+  };
+}
+fn normalize_main_items(items: Vec<MainItem>) -> Vec<MainItem> {
+  let mut normalized: Vec<MainItem> = vec![];
+  let mut reports: Vec<Report> = vec![];
+  let flush = |normalized: &mut Vec<MainItem>, reports: &mut Vec<Report>| {
+    reports.sort_by_key(|report| (report.ty as u8, report.id));
+    for report in reports.drain(..) {
+      match normalized.last_mut() {
+        Some(MainItem::Report(previous)) if previous.ty == report.ty && previous.id == report.id => previous.fields.extend(report.fields),
+        _ => normalized.push(MainItem::Report(report)),
+      };
+    };
+  };
+  for item in items {
+    match item {
+      MainItem::Report(mut report) => {
+        report.fields.iter_mut().for_each(normalize_field);
+        reports.push(report);
+      },
+      MainItem::Collection(mut collection) => {
+        flush(&mut normalized, &mut reports);
+        collection.items = normalize_main_items(collection.items);
+        normalized.push(MainItem::Collection(collection));
+      },
+    };
+  };
+  flush(&mut normalized, &mut reports);
+  normalized
+}
+impl Descriptor {
+  pub fn normalize(self) -> Self {
+    Descriptor { items: normalize_main_items(self.items) }
+  }
+}
+pub fn normalize(items: Vec<DescriptorItem>) -> Result<Vec<DescriptorItem>> {
+  Ok(Descriptor::try_from_descriptor_items(items)?.normalize().into_descriptor_items())
+}
+pub fn normalize_bytes(data: &[u8]) -> Result<Vec<u8>> {
+  try_items_into_bytes(&Descriptor::try_from_bytes(data)?.normalize().into_descriptor_items())
+}
+pub fn fingerprint(data: &[u8]) -> Result<u64> {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in normalize_bytes(data)? {
+    hash ^= byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  };
+  Ok(hash)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::error::Error;
+  const KEYBOARD: [u8; 37] = [
+    0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x85, 0x01,
+    0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00,
+    0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02,
+    0x85, 0x02, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05,
+    0x95, 0x05, 0x91, 0x02, 0xC0,
+  ];
+  #[test]
+  fn equivalent_encodings_share_a_fingerprint() {
+    let mut padded = KEYBOARD.to_vec();
+    padded.splice(16..18, [0x26, 0x01, 0x00, 0x35, 0x00, 0x45, 0x00, 0x65, 0x00]);
+    assert_eq!(fingerprint(&padded), fingerprint(&KEYBOARD));
+    let mut reordered = KEYBOARD[..6].to_vec();
+    reordered.extend([0x85, 0x02, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x05, 0x91, 0x02]);
+    reordered.extend([0x85, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x95, 0x08, 0x81, 0x02, 0xC0]);
+    assert_eq!(normalize_bytes(&reordered), normalize_bytes(&KEYBOARD));
+    let mut changed = KEYBOARD.to_vec();
+    changed[33] = 0x03;
+    assert_ne!(fingerprint(&changed), fingerprint(&KEYBOARD));
+  }
+  #[test]
+  fn drops_zero_physical_ranges_and_units() {
+    let plain = [0x05, 0x01, 0x09, 0x30, 0x15, 0x00, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02];
+    let mut data = plain.to_vec();
+    data.splice(8..8, [0x35, 0x00, 0x45, 0x00, 0x55, 0x00, 0x65, 0x00]);
+    assert_eq!(normalize_bytes(&data), normalize_bytes(&plain));
+    let descriptor = Descriptor::try_from_bytes(&data).unwrap().normalize();
+    assert_eq!(descriptor, Descriptor::try_from_bytes(&plain).unwrap());
+  }
+  #[test]
+  fn rejects_invalid_unit_exponents() {
+    let data = [0x55, 0x10, 0x15, 0x00, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02];
+    assert_eq!(normalize_bytes(&data), Err(Error::InvalidUnitExponent { offset: 0, value: 16 }));
+    assert_eq!(fingerprint(&data), Err(Error::InvalidUnitExponent { offset: 0, value: 16 }));
+  }
+  #[test]
+  fn normalization_is_idempotent() {
+    let items = items_from_bytes(&KEYBOARD).collect::<Result<Vec<_>>>().unwrap();
+    let normalized = normalize(items).unwrap();
+    assert_eq!(normalize(normalized.clone()), Ok(normalized));
+  }
+}
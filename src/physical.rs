@@ -0,0 +1,113 @@
+use crate::descriptors::*;
+use crate::error::Result;
+use crate::reports::*;
+use crate::units::normalize_unit_exponent;
+impl ReportField {
+  pub fn physical_range(&self) -> Option<(i32, i32)> {
+    match *self {
+      ReportField::Variable { logical_minimum, logical_maximum, physical_minimum, physical_maximum, .. } => match (physical_minimum.unwrap_or(0), physical_maximum.unwrap_or(0)) {
+        (0, 0) => Some((logical_minimum, logical_maximum)),
+        range => Some(range),
+      },
+      ReportField::Array { .. } => None,
+    }
+  }
+  pub fn unit_scale(&self) -> f64 {
+    match *self {
+      ReportField::Variable { unit_exponent, .. } => 10f64.powi(normalize_unit_exponent(unit_exponent.unwrap_or(0)) as i32),
+      ReportField::Array { .. } => 1.0,
+    }
+  }
+  pub fn resolution(&self) -> Option<f64> {
+    let ReportField::Variable { logical_minimum, logical_maximum, .. } = *self else {
+      return None;
+    };
+    let (physical_minimum, physical_maximum) = self.physical_range()?;
+    if physical_maximum == physical_minimum {
+      return None;
+    };
+    Some((logical_maximum as f64-logical_minimum as f64)/((physical_maximum as f64-physical_minimum as f64)*self.unit_scale()))
+  }
+  pub fn logical_to_physical(&self, value: i64) -> Option<f64> {
+    let ReportField::Variable { logical_minimum, logical_maximum, .. } = *self else {
+      return None;
+    };
+    let (physical_minimum, physical_maximum) = self.physical_range()?;
+    if logical_maximum == logical_minimum {
+      return Some(physical_minimum as f64*self.unit_scale());
+    };
+    let physical = (value as f64-logical_minimum as f64)*(physical_maximum as f64-physical_minimum as f64)/(logical_maximum as f64-logical_minimum as f64)+physical_minimum as f64;
+    Some(physical*self.unit_scale())
+  }
+  pub fn physical_to_logical(&self, value: f64) -> Option<i64> {
+    let ReportField::Variable { logical_minimum, logical_maximum, .. } = *self else {
+      return None;
+    };
+    let (physical_minimum, physical_maximum) = self.physical_range()?;
+    if physical_maximum == physical_minimum || !value.is_finite() {
+      return None;
+    };
+    let physical = value/self.unit_scale();
+    let logical = ((physical-physical_minimum as f64)*(logical_maximum as f64-logical_minimum as f64)/(physical_maximum as f64-physical_minimum as f64)+logical_minimum as f64).round();
+    if logical < logical_minimum as f64 || logical > logical_maximum as f64 {
+      return None;
+    };
+    Some(logical as i64)
+  }
+}
+pub fn physical_value(field: &ReportField, value: ReportFieldValue) -> Option<f64> {
+  match value {
+    ReportFieldValue::UnsignedVariable(value) => field.logical_to_physical(value? as i64),
+    ReportFieldValue::SignedVariable(value) => field.logical_to_physical(value? as i64),
+    ReportFieldValue::Array(_) | ReportFieldValue::Bytes(_) => None,
+  }
+}
+pub fn logical_value(field: &ReportField, value: f64) -> Option<ReportFieldValue> {
+  let logical = field.physical_to_logical(value)?;
+  Some(ReportFieldValue::from_value(field, logical as i32))
+}
+pub fn read_physical_values(report: &Report, data: &[u8]) -> Result<Vec<Option<f64>>> {
+  let values = read_report_from(report, data)?;
+  Ok(report.fields.iter().zip(values).map(|(field, value)| physical_value(field, value)).collect())
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const DIGITIZER: [u8; 36] = [
+    0x15, 0x00, 0x26, 0xFF, 0x0F, 0x35, 0x00, 0x46,
+    0xE8, 0x03, 0x55, 0xFF, 0x65, 0x11, 0x75, 0x10,
+    0x95, 0x01, 0x81, 0x02, 0x35, 0x00, 0x45, 0x00,
+    0x55, 0x00, 0x65, 0x00, 0x81, 0x02, 0x15, 0x81,
+    0x25, 0x7F, 0x81, 0x02,
+  ];
+  fn report() -> Report {
+    Descriptor::try_from_bytes(&DIGITIZER).unwrap().reports().remove(0)
+  }
+  #[test]
+  fn converts_with_physical_range_and_exponent() {
+    let field = &report().fields[0];
+    assert_eq!(field.physical_range(), Some((0, 1000)));
+    assert_eq!(field.unit_scale(), 0.1);
+    assert_eq!(field.resolution(), Some(40.95));
+    assert_eq!(field.logical_to_physical(4095), Some(100.0));
+    assert_eq!(field.logical_to_physical(0), Some(0.0));
+    assert_eq!(field.physical_to_logical(50.0), Some(2048));
+    assert_eq!(field.physical_to_logical(100.1), None);
+    assert_eq!(field.physical_to_logical(f64::NAN), None);
+    assert_eq!(logical_value(field, 100.0), Some(ReportFieldValue::UnsignedVariable(Some(4095))));
+  }
+  #[test]
+  fn defaults_to_the_logical_range() {
+    let report = report();
+    assert_eq!(report.fields[1].physical_range(), Some((0, 4095)));
+    assert_eq!(report.fields[1].resolution(), Some(1.0));
+    assert_eq!(report.fields[2].physical_range(), Some((-127, 127)));
+    assert_eq!(physical_value(&report.fields[2], ReportFieldValue::SignedVariable(Some(-5))), Some(-5.0));
+    assert_eq!(logical_value(&report.fields[2], -5.4), Some(ReportFieldValue::SignedVariable(Some(-5))));
+    assert_eq!(physical_value(&report.fields[2], ReportFieldValue::SignedVariable(None)), None);
+  }
+  #[test]
+  fn reads_physical_values() {
+    assert_eq!(read_physical_values(&report(), &[0xFF, 0x0F, 0x10, 0x00, 0xFE, 0xFF]), Ok(vec![Some(100.0), Some(16.0), Some(-2.0)]));
+  }
+}
@@ -0,0 +1,168 @@
+use bitvec::prelude::*;
+use crate::descriptor_items::CollectionType;
+use crate::descriptors::*;
+use crate::error::{Error, Result};
+use crate::reports::*;
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldHandle {
+  pub ty: ReportType,
+  pub id: Option<u8>,
+  pub index: usize,
+  pub bit_offset: usize,
+  pub usage: u32,
+  pub application: Option<u32>,
+  pub collections: Vec<(CollectionType, Option<u32>)>,
+  pub field: ReportField,
+}
+impl FieldHandle {
+  fn check_length(&self, length: usize) -> Result<()> {
+    check_field_size(&self.field, self.index)?;
+    let expected = (self.bit_offset+self.field.size() as usize).div_ceil(8).max(self.id.map_or(0, |_| 1));
+    if length < expected {
+      return Err(Error::ReportTooShort { expected, found: length });
+    };
+    Ok(())
+  }
+  pub fn read(&self, data: &[u8]) -> Result<ReportFieldValue> {
+    self.check_length(data.len())?;
+    if let Some(id) = self.id && data[0] != id {
+      return Err(Error::ReportIdMismatch { expected: id, found: data[0] });
+    };
+    let bits = &data.view_bits::<Lsb0>()[self.bit_offset..self.bit_offset+self.field.size() as usize];
+    Ok(read_field(&self.field, bits))
+  }
+  pub fn write(&self, value: &ReportFieldValue, output: &mut [u8]) -> Result<()> {
+    self.check_length(output.len())?;
+    let bits = &mut output.view_bits_mut::<Lsb0>()[self.bit_offset..self.bit_offset+self.field.size() as usize];
+    write_field(&self.field, self.index, value, bits)?;
+    if let Some(id) = self.id {
+      output[0] = id;
+    };
+    Ok(())
+  }
+}
+fn field_matches(field: &ReportField, usage: u32) -> bool {
+  match field {
+    ReportField::Variable { usages, .. } => usages.as_ref().is_some_and(|usages| usages.contains(&usage)),
+    ReportField::Array { usage_range, .. } => usage_range.contains(&usage),
+  }
+}
+struct Search<'a> {
+  usage: u32,
+  application: Option<u32>,
+  reports: &'a [Report],
+  counts: Vec<(ReportType, Option<u8>, usize)>,
+  path: Vec<(CollectionType, Option<u32>)>,
+  handles: Vec<FieldHandle>,
+}
+impl Search<'_> {
+  fn visit(&mut self, items: &[MainItem]) {
+    for item in items {
+      match item {
+        MainItem::Collection(collection) => {
+          self.path.push((collection.ty, collection.usage));
+          self.visit(&collection.items);
+          self.path.pop();
+        },
+        MainItem::Report(report) => {
+          let position = match self.counts.iter().position(|(ty, id, _)| *ty == report.ty && *id == report.id) {
+            Some(position) => position,
+            None => {
+              self.counts.push((report.ty, report.id, 0));
+              self.counts.len()-1
+            },
+          };
+          let base = self.counts[position].2;
+          self.counts[position].2 += report.fields.len();
+          let application = self.path.iter().rev().find(|(ty, _)| *ty == CollectionType::Application).and_then(|(_, usage)| *usage);
+          if self.application.is_some() && self.application != application {
+            continue;
+          };
+          let Some(merged) = self.reports.iter().find(|merged| merged.ty == report.ty && merged.id == report.id) else {
+            continue;
+          };
+          let layout = merged.layout();
+          for (offset, field) in report.fields.iter().enumerate() {
+            if !field_matches(field, self.usage) {
+              continue;
+            };
+            let index = base+offset;
+            self.handles.push(FieldHandle {
+              ty: report.ty,
+              id: report.id,
+              index,
+              bit_offset: layout.field_bit_offset(index).unwrap(),
+              usage: self.usage,
+              application,
+              collections: self.path.clone(),
+              field: field.clone(),
+            });
+          };
+        },
+      };
+    };
+  }
+}
+impl Descriptor {
+  pub fn find_fields(&self, usage: u32, application: Option<u32>) -> Vec<FieldHandle> {
+    let reports = self.reports();
+    let mut search = Search { usage, application, reports: &reports, counts: vec![], path: vec![], handles: vec![] };
+    search.visit(&self.items);
+    search.handles
+  }
+  pub fn find_field(&self, usage: u32, application: Option<u32>) -> Option<FieldHandle> {
+    self.find_fields(usage, application).into_iter().next()
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const MOUSE: [u8; 47] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x85, 0x02,
+    0x05, 0x09, 0x19, 0x01, 0x29, 0x03, 0x15, 0x00,
+    0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02,
+    0x95, 0x01, 0x75, 0x05, 0x81, 0x03, 0x05, 0x01,
+    0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F,
+    0x75, 0x08, 0x95, 0x02, 0x81, 0x06, 0xC0,
+  ];
+  fn x() -> FieldHandle {
+    Descriptor::try_from_bytes(&MOUSE).unwrap().find_field(0x0001_0030, Some(0x0001_0002)).unwrap()
+  }
+  #[test]
+  fn reads_and_writes_fields() {
+    let handle = x();
+    assert_eq!((handle.id, handle.index, handle.bit_offset), (Some(2), 4, 16));
+    let mut output = [0; 4];
+    handle.write(&ReportFieldValue::SignedVariable(Some(-3)), &mut output).unwrap();
+    assert_eq!(output, [0x02, 0x00, 0xFD, 0x00]);
+    assert_eq!(handle.read(&output), Ok(ReportFieldValue::SignedVariable(Some(-3))));
+    assert_eq!(handle.read(&[0x01, 0x00, 0xFD]), Err(Error::ReportIdMismatch { expected: 2, found: 1 }));
+  }
+  #[test]
+  fn rejects_before_writing() {
+    let handle = x();
+    let mut output = [0; 4];
+    assert_eq!(handle.write(&ReportFieldValue::SignedVariable(Some(-128)), &mut output), Err(Error::ValueOutOfRange { field: 4 }));
+    assert_eq!(output, [0; 4]);
+    let mut output = [0; 2];
+    assert_eq!(handle.write(&ReportFieldValue::SignedVariable(Some(1)), &mut output), Err(Error::ReportTooShort { expected: 3, found: 2 }));
+    assert_eq!(output, [0; 2]);
+    assert_eq!(handle.read(&[]), Err(Error::ReportTooShort { expected: 3, found: 0 }));
+  }
+  #[test]
+  fn rejects_unsupported_sizes() {
+    let mut handle = x();
+    if let ReportField::Variable { size, .. } = &mut handle.field {
+      *size = 0;
+    };
+    let mut output = [0; 4];
+    assert_eq!(handle.read(&output), Err(Error::UnsupportedFieldSize { field: 4, size: 0 }));
+    assert_eq!(handle.write(&ReportFieldValue::SignedVariable(None), &mut output), Err(Error::UnsupportedFieldSize { field: 4, size: 0 }));
+    assert_eq!(output, [0; 4]);
+    handle.field = ReportField::Array { size: 40, logical_minimum: 0, logical_maximum: 1, usage_range: vec![0x0001_0030], designator_range: vec![], string_range: vec![], constant: false, relative: false };
+    let mut output = [0; 8];
+    assert_eq!(handle.read(&output), Err(Error::UnsupportedFieldSize { field: 4, size: 40 }));
+    assert_eq!(handle.write(&ReportFieldValue::Array(None), &mut output), Err(Error::UnsupportedFieldSize { field: 4, size: 40 }));
+    assert_eq!(output, [0; 8]);
+  }
+}
@@ -0,0 +1,109 @@
+use crate::descriptors::*;
+use crate::error::{Error, Result};
+use crate::reports::*;
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReportMap {
+  reports: Vec<(Report, usize)>,
+}
+impl ReportMap {
+  pub fn new(descriptor: &Descriptor) -> Self {
+    ReportMap {
+      reports: descriptor.reports().into_iter().map(|report| {
+        let length = report.layout().byte_length();
+        (report, length)
+      }).collect(),
+    }
+  }
+  pub fn reports(&self) -> impl Iterator<Item = &Report> {
+    self.reports.iter().map(|(report, _)| report)
+  }
+  pub fn report(&self, ty: ReportType, id: Option<u8>) -> Option<&Report> {
+    self.reports().find(|report| report.ty == ty && report.id == id)
+  }
+  pub fn is_numbered(&self, ty: ReportType) -> bool {
+    self.reports().any(|report| report.ty == ty && report.id.is_some())
+  }
+  pub fn find(&self, ty: ReportType, data: &[u8]) -> Result<&Report> {
+    let id = if self.is_numbered(ty) {
+      Some(*data.first().ok_or(Error::ReportTooShort { expected: 1, found: 0 })?)
+    }
+    else {
+      None
+    };
+    let Some((report, length)) = self.reports.iter().find(|(report, _)| report.ty == ty && report.id == id) else {
+      return Err(Error::UnknownReport { ty, id });
+    };
+    if data.len() < *length {
+      return Err(Error::ReportTooShort { expected: *length, found: data.len() });
+    };
+    Ok(report)
+  }
+  pub fn read(&self, ty: ReportType, data: &[u8]) -> Result<(&Report, Vec<ReportFieldValue>)> {
+    let report = self.find(ty, data)?;
+    Ok((report, read_report_from(report, data)?))
+  }
+  pub fn read_input(&self, data: &[u8]) -> Result<(&Report, Vec<ReportFieldValue>)> {
+    self.read(ReportType::Input, data)
+  }
+  pub fn write(&self, ty: ReportType, id: Option<u8>, values: &[ReportFieldValue], output: &mut [u8]) -> Result<usize> {
+    let report = self.report(ty, id).ok_or(Error::UnknownReport { ty, id })?;
+    write_report_into(report, values, output)
+  }
+}
+impl From<&Descriptor> for ReportMap {
+  fn from(descriptor: &Descriptor) -> Self {
+    ReportMap::new(descriptor)
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const NUMBERED: [u8; 31] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x85, 0x01,
+    0x09, 0x30, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08,
+    0x95, 0x01, 0x81, 0x02, 0x85, 0x02, 0x09, 0x31,
+    0x95, 0x02, 0x81, 0x02, 0x91, 0x02, 0xC0,
+  ];
+  const MOUSE: [u8; 45] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x05, 0x09,
+    0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01,
+    0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01,
+    0x75, 0x05, 0x81, 0x03, 0x05, 0x01, 0x09, 0x30,
+    0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08,
+    0x95, 0x02, 0x81, 0x06, 0xC0,
+  ];
+  fn numbered() -> ReportMap {
+    ReportMap::new(&Descriptor::try_from_bytes(&NUMBERED).unwrap())
+  }
+  #[test]
+  fn finds_numbered_reports() {
+    let map = numbered();
+    let (report, values) = map.read_input(&[0x02, 0x05, 0xFB]).unwrap();
+    assert_eq!(report.id, Some(2));
+    assert_eq!(values, [ReportFieldValue::SignedVariable(Some(5)), ReportFieldValue::SignedVariable(Some(-5))]);
+    let (report, values) = map.read_input(&[0x01, 0x7F, 0x00, 0x00]).unwrap();
+    assert_eq!(report.id, Some(1));
+    assert_eq!(values, [ReportFieldValue::SignedVariable(Some(127))]);
+    assert_eq!(map.find(ReportType::Output, &[0x02, 0x00, 0x00]).map(|report| report.fields.len()), Ok(2));
+    let mut output = [0; 3];
+    assert_eq!(map.write(ReportType::Output, Some(2), &[values[0].clone(), values[0].clone()], &mut output), Ok(3));
+    assert_eq!(output, [0x02, 0x7F, 0x7F]);
+  }
+  #[test]
+  fn finds_unnumbered_reports() {
+    let map = ReportMap::new(&Descriptor::try_from_bytes(&MOUSE).unwrap());
+    assert!(!map.is_numbered(ReportType::Input));
+    let (report, values) = map.read_input(&[0x01, 0x05, 0xFB, 0x00]).unwrap();
+    assert_eq!(report.id, None);
+    assert_eq!(values.len(), report.fields.len());
+    assert_eq!(map.read(ReportType::Output, &[0x00]), Err(Error::UnknownReport { ty: ReportType::Output, id: None }));
+  }
+  #[test]
+  fn rejects_unknown_and_short_reports() {
+    let map = numbered();
+    assert_eq!(map.read_input(&[0x03, 0x00, 0x00]), Err(Error::UnknownReport { ty: ReportType::Input, id: Some(3) }));
+    assert_eq!(map.read_input(&[]), Err(Error::ReportTooShort { expected: 1, found: 0 }));
+    assert_eq!(map.read_input(&[0x02, 0x05]), Err(Error::ReportTooShort { expected: 3, found: 2 }));
+    assert_eq!(map.write(ReportType::Feature, Some(1), &[], &mut [0; 2]), Err(Error::UnknownReport { ty: ReportType::Feature, id: Some(1) }));
+  }
+}
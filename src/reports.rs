@@ -1,149 +1,635 @@
 use bitvec::prelude::*;
 use crate::descriptors::*;
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+use crate::error::{Error, Result};
+pub use hid_derive::HidReport;
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ReportFieldValue {
   UnsignedVariable(Option<u32>),
   SignedVariable(Option<i32>),
   Array(Option<u32>),
+  Bytes(Vec<u8>),
+}
+pub(crate) fn check_field_size(field: &ReportField, index: usize) -> Result<()> {
+  let size = field.size();
+  if size == 0 || (size > 32 && !field.is_bytes()) {
+    return Err(Error::UnsupportedFieldSize { field: index, size });
+  };
+  Ok(())
+}
+fn report_length(report: &Report) -> Result<usize> {
+  let mut length: usize = if report.id.is_some() { 8 } else { 0 };
+  for (index, field) in report.fields.iter().enumerate() {
+    check_field_size(field, index)?;
+    length += field.size() as usize;
+  };
+  Ok(length)
 }
 pub fn write_report(report: Report, values: Vec<ReportFieldValue>) -> BitVec<u8, Lsb0> {
-  let mut data = BitVec::<u8, Lsb0>::new();
+  try_write_report(report, values).unwrap_or_else(|error| panic!("{error}"))
+}
+pub fn try_write_report(report: Report, values: Vec<ReportFieldValue>) -> Result<BitVec<u8, Lsb0>> {
+  let length = report_length(&report)?;
+  let mut data = vec![0; length.div_ceil(8)];
+  write_report_into(&report, &values, &mut data)?;
+  let mut data = BitVec::from_vec(data);
+  data.truncate(length);
+  Ok(data)
+}
+pub fn write_report_into(report: &Report, values: &[ReportFieldValue], output: &mut [u8]) -> Result<usize> {
+  let length = report_length(report)?;
+  if report.fields.len() != values.len() {
+    return Err(Error::ValueCountMismatch { expected: report.fields.len(), found: values.len() });
+  };
+  if output.len() < length.div_ceil(8) {
+    return Err(Error::BufferTooSmall { expected: length.div_ceil(8), found: output.len() });
+  };
+  let data = output.view_bits_mut::<Lsb0>();
   let mut cursor: usize = 0;
   if let Some(id) = report.id {
-    data.resize(8, false);
-    data.store::<u8>(id);
+    data[0..8].store_le::<u8>(id);
     cursor = 8;
   };
-  if report.fields.len() != values.len() {
-    panic!("Either not enough or too many values provided");
-  };
-  for (field, value) in report.fields.into_iter().zip(values.into_iter()) {
-    match (field, value) {
-      (ReportField::Variable { size, logical_minimum, logical_maximum, .. }, ReportFieldValue::UnsignedVariable(value)) if logical_minimum >= 0 && logical_maximum >= 0 => {
-        if let Some(value) = value {
-          if value < logical_minimum as u32 || value > logical_maximum as u32 {
-            panic!("Value is out of the specified range")
-          };
-          data.resize(cursor+(size as usize), false);
-          data[cursor..cursor+(size as usize)].store_be::<u32>(value);
-          cursor += size as usize;
-        }
+  for (index, (field, value)) in report.fields.iter().zip(values).enumerate() {
+    let size = field.size() as usize;
+    write_field(field, index, value, &mut data[cursor..cursor+size])?;
+    cursor += size;
+  };
+  data[cursor..length.div_ceil(8)*8].fill(false);
+  Ok(length.div_ceil(8))
+}
+pub(crate) fn write_field(field: &ReportField, index: usize, value: &ReportFieldValue, data: &mut BitSlice<u8, Lsb0>) -> Result<()> {
+  if field.is_bytes() {
+    let ReportFieldValue::Bytes(bytes) = value else {
+      return Err(Error::ValueTypeMismatch { field: index });
+    };
+    if bytes.len() > data.len().div_ceil(8) {
+      return Err(Error::ValueOutOfRange { field: index });
+    };
+    for (chunk, byte) in data.chunks_mut(8).zip(bytes.iter().copied().chain(std::iter::repeat(0))) {
+      chunk.store_le::<u8>(byte);
+    };
+    return Ok(());
+  };
+  match (field, value) {
+    (&ReportField::Variable { size, logical_minimum, logical_maximum, .. }, &ReportFieldValue::UnsignedVariable(value)) if logical_minimum >= 0 && logical_maximum >= 0 => {
+      if let Some(value) = value {
+        if value < logical_minimum as u32 || value > logical_maximum as u32 {
+          return Err(Error::ValueOutOfRange { field: index });
+        };
+        data.store_le::<u32>(value);
+      }
+      else {
+        let null_value: u32 = if logical_minimum != 0 {
+          0
+          }
         else {
-          let null_value: u32 = if logical_minimum != 0 {
-            0
-            }
-          else {
-            if logical_maximum as u32 >= 2u32.pow(size)-1 {
-              panic!("The logical minimum and logical maximum contain all possible values")
-            };
-            (logical_maximum as u32)+1
+          if logical_maximum as u32 >= u32::MAX >> (32-size) {
+            return Err(Error::NoNullValue { field: index });
           };
-          data.resize(cursor+(size as usize), false);
-          data[cursor..cursor+(size as usize)].store_be::<u32>(null_value);
-          cursor += size as usize;
+          (logical_maximum as u32)+1
         };
-      },
-      (ReportField::Variable { size, logical_minimum, logical_maximum, .. }, ReportFieldValue::SignedVariable(value)) if logical_minimum < 0 || logical_maximum < 0 => {
-        if let Some(value) = value {
-          if value < logical_minimum || value > logical_maximum {
-            panic!("Value is out of the specified range")
-          };
-          data.resize(cursor+(size as usize), false);
-          data[cursor..cursor+(size as usize)].store_be::<i32>(value);
-          cursor += size as usize;
-        }
+        data.store_le::<u32>(null_value);
+      };
+    },
+    (&ReportField::Variable { size, logical_minimum, logical_maximum, .. }, &ReportFieldValue::SignedVariable(value)) if logical_minimum < 0 || logical_maximum < 0 => {
+      if let Some(value) = value {
+        if value < logical_minimum || value > logical_maximum {
+          return Err(Error::ValueOutOfRange { field: index });
+        };
+        data.store_le::<i32>(value);
+      }
+      else {
+        let null_value: i32 = if logical_minimum > i32::MIN >> (32-size) {
+          logical_minimum-1
+          }
         else {
-          let null_value: i32 = if logical_minimum >= -2i32.pow(size)+1 {
-            logical_minimum-1
-            }
-          else {
-            if logical_maximum >= 2i32.pow(size)-1 {
-              panic!("The logical minimum and logical maximum contain all possible values")
-            };
-            logical_maximum+1
+          if logical_maximum >= i32::MAX >> (32-size) {
+            return Err(Error::NoNullValue { field: index });
           };
-          data.resize(cursor+(size as usize), false);
-          data[cursor..cursor+(size as usize)].store_be::<i32>(null_value);
-          cursor += size as usize;
+          logical_maximum+1
         };
-      },
-      (ReportField::Array { size, logical_minimum, logical_maximum, usage_range, .. }, ReportFieldValue::Array(usage)) => {
-        if let Some(usage) = usage {
-          if !usage_range.contains(&usage) {
-            panic!("Usage is out of the specified range")
-          };
-          let value = usage_range.iter().position(|usage2| usage==*usage2).unwrap() as u32 + logical_minimum as u32;
-          if value > logical_maximum as u32 {
-            panic!("Value is out of the specified range")
-          };
-          data.resize(cursor+(size as usize), false);
-          data[cursor..cursor+(size as usize)].store_be::<u32>(value);
-          cursor += size as usize;
-        }
+        data.store_le::<i32>(null_value);
+      };
+    },
+    (&ReportField::Array { size, logical_minimum, logical_maximum, ref usage_range, constant, .. }, &ReportFieldValue::Array(usage)) => {
+      if let Some(usage) = usage {
+        let Some(position) = usage_range.iter().position(|usage2| usage==*usage2) else {
+          return Err(Error::UsageOutOfRange { field: index });
+        };
+        let value = position as u32 + logical_minimum as u32;
+        if value > logical_maximum as u32 {
+          return Err(Error::ValueOutOfRange { field: index });
+        };
+        data.store_le::<u32>(value);
+      }
+      else {
+        let null_value: u32 = if constant || logical_minimum != 0 {
+          0
+          }
         else {
-          let null_value: u32 = if logical_minimum != 0 {
-            0
-            }
-          else {
-            if logical_maximum as u32 >= 2u32.pow(size)-1 {
-              panic!("The logical minimum and logical maximum contain all possible values")
-            };
-            (logical_maximum as u32)+1
+          if logical_maximum as u32 >= u32::MAX >> (32-size) {
+            return Err(Error::NoNullValue { field: index });
           };
-          data.resize(cursor+(size as usize), false);
-          data[cursor..cursor+(size as usize)].store_be::<u32>(null_value);
-          cursor += size as usize;
+          (logical_maximum as u32)+1
         };
-      },
-      _ => panic!("Report fields and values do not match"),
-    };
+        data.store_le::<u32>(null_value);
+      };
+    },
+    _ => return Err(Error::ValueTypeMismatch { field: index }),
   };
-  data
+  Ok(())
 }
 pub fn read_report(report: Report, data: BitVec<u8, Lsb0>) -> Vec<ReportFieldValue> {
-  let mut values = vec![];
+  try_read_report(report, data).unwrap_or_else(|error| panic!("{error}"))
+}
+pub fn try_read_report(report: Report, data: BitVec<u8, Lsb0>) -> Result<Vec<ReportFieldValue>> {
+  let length = report_length(&report)?;
+  if data.len() < length {
+    return Err(Error::ReportTooShort { expected: length.div_ceil(8), found: data.len()/8 });
+  };
+  read_report_from(&report, data.as_raw_slice())
+}
+pub fn read_report_from(report: &Report, data: &[u8]) -> Result<Vec<ReportFieldValue>> {
+  let mut values = Vec::with_capacity(report.fields.len());
+  read_report_into(report, data, &mut values)?;
+  Ok(values)
+}
+pub fn read_report_into(report: &Report, data: &[u8], values: &mut Vec<ReportFieldValue>) -> Result<()> {
+  let length = report_length(report)?;
+  if data.len() < length.div_ceil(8) {
+    return Err(Error::ReportTooShort { expected: length.div_ceil(8), found: data.len() });
+  };
+  let data = data.view_bits::<Lsb0>();
+  values.clear();
   let mut cursor: usize = 0;
   if let Some(id) = report.id {
-    if data[0..8].load::<u8>() != id {
-      panic!("ID does not match");
+    if data[0..8].load_le::<u8>() != id {
+      return Err(Error::ReportIdMismatch { expected: id, found: data[0..8].load_le::<u8>() });
     };
     cursor += 8;
   };
-  for field in report.fields {
-    match field {
-      ReportField::Variable { size, logical_minimum, logical_maximum, .. } => {
-        if logical_minimum < 0 || logical_maximum < 0 {
-          let value = data[cursor..cursor+(size as usize)].load::<i32>();
-          if value < logical_minimum || value > logical_maximum {
-            values.push(ReportFieldValue::SignedVariable(None));
-          }
-          else {
-            values.push(ReportFieldValue::SignedVariable(Some(value)));
-          };
+  for field in &report.fields {
+    let size = field.size() as usize;
+    values.push(read_field(field, &data[cursor..cursor+size]));
+    cursor += size;
+  };
+  Ok(())
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReportElement {
+  pub index: usize,
+  pub usage: Option<u32>,
+  pub constant: bool,
+  pub value: ReportFieldValue,
+}
+impl ReportElement {
+  pub fn is_active(&self) -> bool {
+    match self.value {
+      ReportFieldValue::UnsignedVariable(value) => value.is_some_and(|value| value != 0),
+      ReportFieldValue::SignedVariable(value) => value.is_some_and(|value| value != 0),
+      ReportFieldValue::Array(usage) => usage.is_some_and(|usage| usage & 0xFFFF != 0),
+      ReportFieldValue::Bytes(ref bytes) => bytes.iter().any(|byte| *byte != 0),
+    }
+  }
+}
+pub fn element_usages(report: &Report) -> Vec<Option<u32>> {
+  report.fields.iter().map(|field| match field {
+    ReportField::Variable { usages, .. } => usages.as_ref().and_then(|usages| usages.first().copied()),
+    ReportField::Array { .. } => None,
+  }).collect()
+}
+pub fn read_report_elements(report: &Report, data: &[u8]) -> Result<Vec<ReportElement>> {
+  let values = read_report_from(report, data)?;
+  Ok(report.fields.iter().zip(element_usages(report)).zip(values).enumerate().map(|(index, ((field, usage), value))| {
+    let usage = match value {
+      ReportFieldValue::Array(usage) => usage,
+      _ => usage,
+    };
+    let constant = match field {
+      ReportField::Variable { constant, .. } | ReportField::Array { constant, .. } => *constant,
+    };
+    ReportElement { index, usage, constant, value }
+  }).collect())
+}
+pub fn active_usages(report: &Report, data: &[u8]) -> Result<Vec<u32>> {
+  Ok(read_report_elements(report, data)?.into_iter().filter(|element| !element.constant && element.is_active()).filter_map(|element| element.usage).collect())
+}
+pub(crate) fn read_field(field: &ReportField, data: &BitSlice<u8, Lsb0>) -> ReportFieldValue {
+  if field.is_bytes() {
+    return ReportFieldValue::Bytes(data.chunks(8).map(|chunk| chunk.load_le::<u8>()).collect());
+  };
+  match *field {
+    ReportField::Variable { logical_minimum, logical_maximum, .. } => {
+      if logical_minimum < 0 || logical_maximum < 0 {
+        let value = data.load_le::<i32>();
+        if value < logical_minimum || value > logical_maximum {
+          ReportFieldValue::SignedVariable(None)
         }
         else {
-          let value = data[cursor..cursor+(size as usize)].load::<u32>();
-          if value < logical_minimum as u32 || value > logical_maximum as u32 {
-            values.push(ReportFieldValue::UnsignedVariable(None));
-          }
-          else {
-            values.push(ReportFieldValue::UnsignedVariable(Some(value)));
-          };
-        };
-        cursor += size as usize;
-      },
-      ReportField::Array { size, logical_minimum, logical_maximum, usage_range, .. } => {
-        let value = data[cursor..cursor+(size as usize)].load::<u32>();
+          ReportFieldValue::SignedVariable(Some(value))
+        }
+      }
+      else {
+        let value = data.load_le::<u32>();
         if value < logical_minimum as u32 || value > logical_maximum as u32 {
-          values.push(ReportFieldValue::Array(None));
+          ReportFieldValue::UnsignedVariable(None)
         }
         else {
-          let usage = usage_range.get((value-logical_minimum as u32) as usize).unwrap();
-          values.push(ReportFieldValue::Array(Some(*usage)));
+          ReportFieldValue::UnsignedVariable(Some(value))
+        }
+      }
+    },
+    ReportField::Array { logical_minimum, logical_maximum, ref usage_range, .. } => {
+      let value = data.load_le::<u32>();
+      if value < logical_minimum as u32 || value > logical_maximum as u32 {
+        ReportFieldValue::Array(None)
+      }
+      else {
+        let usage = usage_range.get((value-logical_minimum as u32) as usize);
+        ReportFieldValue::Array(usage.copied())
+      }
+    },
+  }
+}
+pub trait ReportValue: Sized {
+  const BITS: u32;
+  const SIGNED: bool;
+  fn into_value(self) -> Option<i128>;
+  fn from_value(value: Option<i128>) -> Option<Self>;
+  fn logical_range(size: u32) -> (i32, i32) {
+    let size = size.clamp(1, 32);
+    if Self::SIGNED {
+      ((-1i64 << (size-1)).max(i32::MIN as i64) as i32, ((1i64 << (size-1))-1).min(i32::MAX as i64) as i32)
+    }
+    else {
+      (0, ((1i64 << size)-1).min(i32::MAX as i64) as i32)
+    }
+  }
+}
+macro_rules! impl_report_value {
+  ($ty:ty, $signed:expr) => {
+    impl ReportValue for $ty {
+      const BITS: u32 = <$ty>::BITS;
+      const SIGNED: bool = $signed;
+      fn into_value(self) -> Option<i128> {
+        Some(self as i128)
+      }
+      fn from_value(value: Option<i128>) -> Option<Self> {
+        value.and_then(|value| <$ty>::try_from(value).ok())
+      }
+    }
+  };
+}
+impl_report_value!(u8, false);
+impl_report_value!(u16, false);
+impl_report_value!(u32, false);
+impl_report_value!(u64, false);
+impl_report_value!(i8, true);
+impl_report_value!(i16, true);
+impl_report_value!(i32, true);
+impl_report_value!(i64, true);
+impl ReportValue for bool {
+  const BITS: u32 = 1;
+  const SIGNED: bool = false;
+  fn logical_range(_size: u32) -> (i32, i32) {
+    (0, 1)
+  }
+  fn into_value(self) -> Option<i128> {
+    Some(self as i128)
+  }
+  fn from_value(value: Option<i128>) -> Option<Self> {
+    value.map(|value| value != 0)
+  }
+}
+impl<T: ReportValue> ReportValue for Option<T> {
+  const BITS: u32 = if T::BITS == 1 { 2 } else { T::BITS };
+  const SIGNED: bool = T::SIGNED;
+  fn logical_range(size: u32) -> (i32, i32) {
+    let (minimum, maximum) = T::logical_range(size);
+    let size = size.clamp(1, 32);
+    if minimum < 0 && minimum as i64 == -1i64 << (size-1) {
+      (minimum+1, maximum)
+    }
+    else if minimum >= 0 && maximum as i64 == (1i64 << size)-1 {
+      (minimum, maximum-1)
+    }
+    else {
+      (minimum, maximum)
+    }
+  }
+  fn into_value(self) -> Option<i128> {
+    self.and_then(T::into_value)
+  }
+  fn from_value(value: Option<i128>) -> Option<Self> {
+    match value {
+      Some(value) => T::from_value(Some(value)).map(Some),
+      None => Some(None),
+    }
+  }
+}
+impl ReportFieldValue {
+  pub fn from_value<T: ReportValue>(field: &ReportField, value: T) -> Self {
+    let value = value.into_value();
+    if field.is_bytes() {
+      let length = field.size().div_ceil(8) as usize;
+      let value = value.unwrap_or(0);
+      let fill = if value < 0 { 0xFF } else { 0 };
+      return ReportFieldValue::Bytes(value.to_le_bytes().into_iter().chain(std::iter::repeat(fill)).take(length).collect());
+    };
+    match *field {
+      ReportField::Array { .. } => ReportFieldValue::Array(value.map(|value| value as u32)),
+      ReportField::Variable { logical_minimum, logical_maximum, .. } if logical_minimum < 0 || logical_maximum < 0 => ReportFieldValue::SignedVariable(value.map(|value| value as i32)),
+      ReportField::Variable { .. } => ReportFieldValue::UnsignedVariable(value.map(|value| value as u32)),
+    }
+  }
+  pub fn to_value<T: ReportValue>(&self, field: usize) -> Result<T> {
+    let value = match *self {
+      ReportFieldValue::UnsignedVariable(value) | ReportFieldValue::Array(value) => value.map(i128::from),
+      ReportFieldValue::SignedVariable(value) => value.map(i128::from),
+      ReportFieldValue::Bytes(ref bytes) => {
+        if bytes.len() > 16 {
+          return Err(Error::ValueOutOfRange { field });
         };
-        cursor += size as usize;
+        let mut value = [0; 16];
+        value[..bytes.len()].copy_from_slice(bytes);
+        let value = i128::from_le_bytes(value);
+        let bits = bytes.len()*8;
+        if T::SIGNED && bits > 0 && bits < 128 {
+          Some(value << (128-bits) >> (128-bits))
+        }
+        else {
+          Some(value)
+        }
       },
     };
+    T::from_value(value).ok_or(Error::ValueOutOfRange { field })
+  }
+}
+pub trait HidReport: Sized {
+  fn report() -> Report;
+  fn values(&self, report: &Report) -> Vec<ReportFieldValue>;
+  fn try_from_values(values: &[ReportFieldValue]) -> Result<Self>;
+  fn main_item() -> MainItem {
+    MainItem::Report(Self::report())
+  }
+  fn to_report(&self) -> BitVec<u8, Lsb0> {
+    self.try_to_report().unwrap_or_else(|error| panic!("{error}"))
+  }
+  fn try_to_report(&self) -> Result<BitVec<u8, Lsb0>> {
+    let report = Self::report();
+    let values = self.values(&report);
+    try_write_report(report, values)
+  }
+  fn from_report(data: BitVec<u8, Lsb0>) -> Self {
+    Self::try_from_report(data).unwrap_or_else(|error| panic!("{error}"))
+  }
+  fn try_from_report(data: BitVec<u8, Lsb0>) -> Result<Self> {
+    let values = try_read_report(Self::report(), data)?;
+    Self::try_from_values(&values)
+  }
+}
+fn bit_values(bits: u8, count: usize) -> impl Iterator<Item = ReportFieldValue> {
+  (0..count).map(move |bit| ReportFieldValue::UnsignedVariable(Some(((bits >> bit) & 1) as u32)))
+}
+fn collect_bits(values: &[ReportFieldValue], start: usize) -> Result<u8> {
+  let mut bits = 0;
+  for (bit, value) in values.iter().enumerate() {
+    bits |= (value.to_value::<bool>(start+bit)? as u8) << bit;
+  };
+  Ok(bits)
+}
+fn check_value_count(values: &[ReportFieldValue], expected: usize) -> Result<()> {
+  if values.len() != expected {
+    return Err(Error::ValueCountMismatch { expected, found: values.len() });
   };
-  values
+  Ok(())
+}
+// The boot reports implement HidReport by hand because #[derive(HidReport)] cannot express the
+// Appendix B layouts: bitmaps packed into a u8, constant Array padding, and page-relative key codes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BootKeyboardInputReport {
+  pub modifiers: u8,
+  pub keys: [u8; 6],
+}
+impl HidReport for BootKeyboardInputReport {
+  fn report() -> Report {
+    Descriptor::boot_keyboard().report(ReportType::Input, None).unwrap()
+  }
+  fn values(&self, _report: &Report) -> Vec<ReportFieldValue> {
+    let mut values: Vec<ReportFieldValue> = bit_values(self.modifiers, 8).collect();
+    values.push(ReportFieldValue::Array(None));
+    values.extend(self.keys.iter().map(|key| ReportFieldValue::Array(Some(0x0007_0000 | *key as u32))));
+    values
+  }
+  fn try_from_values(values: &[ReportFieldValue]) -> Result<Self> {
+    check_value_count(values, 15)?;
+    let mut keys = [0; 6];
+    for (index, (key, value)) in keys.iter_mut().zip(&values[9..]).enumerate() {
+      *key = match *value {
+        ReportFieldValue::Array(usage) => usage.map_or(0, |usage| usage as u8),
+        _ => return Err(Error::ValueTypeMismatch { field: 9+index }),
+      };
+    };
+    Ok(BootKeyboardInputReport { modifiers: collect_bits(&values[..8], 0)?, keys })
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BootKeyboardOutputReport {
+  pub leds: u8,
+}
+impl HidReport for BootKeyboardOutputReport {
+  fn report() -> Report {
+    Descriptor::boot_keyboard().report(ReportType::Output, None).unwrap()
+  }
+  fn values(&self, _report: &Report) -> Vec<ReportFieldValue> {
+    let mut values: Vec<ReportFieldValue> = bit_values(self.leds, 5).collect();
+    values.push(ReportFieldValue::Array(None));
+    values
+  }
+  fn try_from_values(values: &[ReportFieldValue]) -> Result<Self> {
+    check_value_count(values, 6)?;
+    Ok(BootKeyboardOutputReport { leds: collect_bits(&values[..5], 0)? })
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BootMouseInputReport {
+  pub buttons: u8,
+  pub x: i8,
+  pub y: i8,
+}
+impl HidReport for BootMouseInputReport {
+  fn report() -> Report {
+    Descriptor::boot_mouse().report(ReportType::Input, None).unwrap()
+  }
+  fn values(&self, _report: &Report) -> Vec<ReportFieldValue> {
+    let mut values: Vec<ReportFieldValue> = bit_values(self.buttons, 3).collect();
+    values.push(ReportFieldValue::Array(None));
+    values.push(ReportFieldValue::SignedVariable(Some(self.x.max(-127) as i32)));
+    values.push(ReportFieldValue::SignedVariable(Some(self.y.max(-127) as i32)));
+    values
+  }
+  fn try_from_values(values: &[ReportFieldValue]) -> Result<Self> {
+    check_value_count(values, 6)?;
+    Ok(BootMouseInputReport {
+      buttons: collect_bits(&values[..3], 0)?,
+      x: values[4].to_value(4)?,
+      y: values[5].to_value(5)?,
+    })
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct WheelMouseInputReport {
+  pub buttons: u8,
+  pub x: i8,
+  pub y: i8,
+  pub wheel: i8,
+}
+impl HidReport for WheelMouseInputReport {
+  fn report() -> Report {
+    Descriptor::wheel_mouse().report(ReportType::Input, None).unwrap()
+  }
+  fn values(&self, _report: &Report) -> Vec<ReportFieldValue> {
+    let mut values: Vec<ReportFieldValue> = bit_values(self.buttons, 3).collect();
+    values.push(ReportFieldValue::Array(None));
+    values.push(ReportFieldValue::SignedVariable(Some(self.x.max(-127) as i32)));
+    values.push(ReportFieldValue::SignedVariable(Some(self.y.max(-127) as i32)));
+    values.push(ReportFieldValue::SignedVariable(Some(self.wheel.max(-127) as i32)));
+    values
+  }
+  fn try_from_values(values: &[ReportFieldValue]) -> Result<Self> {
+    check_value_count(values, 7)?;
+    Ok(WheelMouseInputReport {
+      buttons: collect_bits(&values[..3], 0)?,
+      x: values[4].to_value(4)?,
+      y: values[5].to_value(5)?,
+      wheel: values[6].to_value(6)?,
+    })
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn report(data: &[u8]) -> Report {
+    Descriptor::try_from_bytes(data).unwrap().reports().into_iter().next().unwrap()
+  }
+  fn mouse() -> Report {
+    report(&[
+      0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x85, 0x02,
+      0x05, 0x09, 0x19, 0x01, 0x29, 0x03, 0x15, 0x00,
+      0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02,
+      0x95, 0x01, 0x75, 0x05, 0x81, 0x03, 0x05, 0x01,
+      0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F,
+      0x75, 0x08, 0x95, 0x02, 0x81, 0x06, 0xC0,
+    ])
+  }
+  fn mouse_values(buttons: [u32; 3], x: i32, y: i32) -> Vec<ReportFieldValue> {
+    let mut values: Vec<_> = buttons.into_iter().map(|button| ReportFieldValue::UnsignedVariable(Some(button))).collect();
+    values.push(ReportFieldValue::UnsignedVariable(Some(0)));
+    values.push(ReportFieldValue::SignedVariable(Some(x)));
+    values.push(ReportFieldValue::SignedVariable(Some(y)));
+    values
+  }
+  #[test]
+  fn write_read_round_trip() {
+    let report = mouse();
+    let values = mouse_values([1, 0, 1], -5, 127);
+    let mut output = [0xFF; 4];
+    assert_eq!(write_report_into(&report, &values, &mut output), Ok(4));
+    assert_eq!(output, [0x02, 0x05, 0xFB, 0x7F]);
+    assert_eq!(read_report_from(&report, &output), Ok(values.clone()));
+    let data = try_write_report(report.clone(), values.clone()).unwrap();
+    assert_eq!(data.as_raw_slice(), output);
+    assert_eq!(try_read_report(report, data), Ok(values));
+  }
+  #[test]
+  fn null_values_round_trip() {
+    let report = report(&[0x05, 0x01, 0x09, 0x39, 0x15, 0x00, 0x25, 0x07, 0x75, 0x04, 0x95, 0x01, 0x81, 0x42, 0x75, 0x04, 0x81, 0x03]);
+    let values = vec![ReportFieldValue::UnsignedVariable(None), ReportFieldValue::UnsignedVariable(Some(0))];
+    let mut output = [0; 1];
+    write_report_into(&report, &values, &mut output).unwrap();
+    assert_eq!(output, [0x08]);
+    assert_eq!(read_report_from(&report, &output), Ok(values));
+  }
+  #[test]
+  fn array_fields_round_trip() {
+    let report = report(&[0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x15, 0x00, 0x25, 0x65, 0x75, 0x08, 0x95, 0x02, 0x81, 0x00]);
+    let values = vec![ReportFieldValue::Array(Some(0x0007_0004)), ReportFieldValue::Array(Some(0x0007_0000))];
+    let mut output = [0; 2];
+    write_report_into(&report, &values, &mut output).unwrap();
+    assert_eq!(output, [0x04, 0x00]);
+    assert_eq!(read_report_from(&report, &output), Ok(values));
+    assert_eq!(read_report_from(&report, &[0x66, 0x04]).unwrap()[0], ReportFieldValue::Array(None));
+    let values = vec![ReportFieldValue::Array(Some(0x0007_0066)), ReportFieldValue::Array(None)];
+    assert_eq!(write_report_into(&report, &values, &mut output), Err(Error::UsageOutOfRange { field: 0 }));
+  }
+  #[test]
+  fn bytes_fields_round_trip() {
+    let report = report(&[0x06, 0x00, 0xFF, 0x09, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x30, 0x95, 0x01, 0xB1, 0x02, 0x75, 0x08, 0x95, 0x02, 0xB2, 0x02, 0x01]);
+    assert!(report.fields.iter().all(ReportField::is_bytes));
+    let values = vec![ReportFieldValue::Bytes(vec![0x01, 0x02]), ReportFieldValue::Bytes(vec![0xFE]), ReportFieldValue::Bytes(vec![])];
+    let mut output = [0xFF; 8];
+    assert_eq!(write_report_into(&report, &values, &mut output), Ok(8));
+    assert_eq!(output, [0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0xFE, 0x00]);
+    let read = read_report_from(&report, &output).unwrap();
+    assert_eq!(read[0], ReportFieldValue::Bytes(vec![0x01, 0x02, 0x00, 0x00, 0x00, 0x00]));
+    assert_eq!(read[1].to_value::<i8>(1), Ok(-2));
+    assert_eq!(read[1].to_value::<u8>(1), Ok(0xFE));
+    let values = vec![ReportFieldValue::Bytes(vec![0; 7]), ReportFieldValue::Bytes(vec![]), ReportFieldValue::Bytes(vec![])];
+    assert_eq!(write_report_into(&report, &values, &mut output), Err(Error::ValueOutOfRange { field: 0 }));
+    let values = vec![ReportFieldValue::Bytes(vec![]), ReportFieldValue::UnsignedVariable(Some(1)), ReportFieldValue::Bytes(vec![])];
+    assert_eq!(write_report_into(&report, &values, &mut output), Err(Error::ValueTypeMismatch { field: 1 }));
+  }
+  #[test]
+  fn rejects_invalid_reports() {
+    let report = mouse();
+    let values = mouse_values([1, 0, 1], -5, 127);
+    assert_eq!(write_report_into(&report, &values, &mut [0; 3]), Err(Error::BufferTooSmall { expected: 4, found: 3 }));
+    assert_eq!(write_report_into(&report, &values[1..], &mut [0; 4]), Err(Error::ValueCountMismatch { expected: 6, found: 5 }));
+    assert_eq!(write_report_into(&report, &mouse_values([2, 0, 0], 0, 0), &mut [0; 4]), Err(Error::ValueOutOfRange { field: 0 }));
+    assert_eq!(write_report_into(&report, &mouse_values([0, 0, 0], -128, 0), &mut [0; 4]), Err(Error::ValueOutOfRange { field: 4 }));
+    assert_eq!(read_report_from(&report, &[0x02, 0x00, 0x00]), Err(Error::ReportTooShort { expected: 4, found: 3 }));
+    assert_eq!(read_report_from(&report, &[0x03, 0x00, 0x00, 0x00]), Err(Error::ReportIdMismatch { expected: 2, found: 3 }));
+  }
+  #[test]
+  fn elements_use_their_own_usages() {
+    let report = report(&[
+      0x05, 0x01, 0x09, 0x30, 0x15, 0x00, 0x25, 0x01,
+      0x75, 0x01, 0x95, 0x01, 0x81, 0x02, 0x81, 0x02,
+      0x09, 0x31, 0x09, 0x32, 0x95, 0x03, 0x81, 0x02,
+      0x95, 0x01, 0x81, 0x03,
+    ]);
+    assert_eq!(element_usages(&report), [Some(0x0001_0030), None, Some(0x0001_0031), Some(0x0001_0032), Some(0x0001_0032), None]);
+    let elements = read_report_elements(&report, &[0b0011_1111]).unwrap();
+    assert_eq!(elements.iter().map(|element| element.usage).collect::<Vec<_>>(), element_usages(&report));
+    assert_eq!(active_usages(&report, &[0b0011_1111]), Ok(vec![0x0001_0030, 0x0001_0031, 0x0001_0032, 0x0001_0032]));
+  }
+  #[test]
+  fn boot_keyboard_reports() {
+    let report = BootKeyboardInputReport { modifiers: 0b0000_0010, keys: [0x04, 0x05, 0, 0, 0, 0] };
+    let data = report.to_report();
+    assert_eq!(data.as_raw_slice(), [0x02, 0x00, 0x04, 0x05, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(BootKeyboardInputReport::from_report(data), report);
+    let report = BootKeyboardOutputReport { leds: 0b0001_0101 };
+    let data = report.to_report();
+    assert_eq!(data.as_raw_slice(), [0x15]);
+    assert_eq!(BootKeyboardOutputReport::from_report(data), report);
+  }
+  #[test]
+  fn boot_mouse_reports() {
+    let report = BootMouseInputReport { buttons: 0b101, x: 5, y: -5 };
+    let data = report.to_report();
+    assert_eq!(data.as_raw_slice(), [0x05, 0x05, 0xFB]);
+    assert_eq!(BootMouseInputReport::from_report(data), report);
+    let report = WheelMouseInputReport { buttons: 0b010, x: -1, y: 127, wheel: 1 };
+    let data = report.to_report();
+    assert_eq!(data.as_raw_slice(), [0x02, 0xFF, 0x7F, 0x01]);
+    assert_eq!(WheelMouseInputReport::from_report(data), report);
+  }
+  #[test]
+  fn boot_mouse_reports_clamp_to_the_logical_range() {
+    let report = BootMouseInputReport { buttons: 0, x: -128, y: i8::MIN };
+    assert_eq!(report.try_to_report().unwrap().as_raw_slice(), [0x00, 0x81, 0x81]);
+    let report = WheelMouseInputReport { buttons: 0, x: 0, y: 0, wheel: -128 };
+    assert_eq!(report.try_to_report().unwrap().as_raw_slice(), [0x00, 0x00, 0x00, 0x81]);
+  }
 }
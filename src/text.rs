@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use hut::{Usage, UsagePage};
+use crate::descriptor_items::*;
+use crate::display::{annotate, AnnotationState};
+use crate::error::{Error, Result};
+use crate::units::Unit;
+pub fn items_to_text(items: &[DescriptorItem]) -> String {
+  let mut text = String::new();
+  let mut state = AnnotationState::default();
+  let mut depth: usize = 0;
+  for item in items {
+    if *item == DescriptorItem::EndCollection {
+      depth = depth.saturating_sub(1);
+    };
+    text.push_str(&"  ".repeat(depth));
+    text.push_str(&annotate(item, &mut state));
+    text.push('\n');
+    if let DescriptorItem::Collection(_) = item {
+      depth += 1;
+    };
+  };
+  text
+}
+fn normalize(text: &str) -> String {
+  text.chars().filter(|character| !character.is_whitespace() && *character != '_' && *character != '-').flat_map(char::to_lowercase).collect()
+}
+fn parse_number(text: &str) -> Option<i64> {
+  let text = text.trim();
+  let (negative, text) = match text.strip_prefix('-') {
+    Some(text) => (true, text),
+    None => (false, text),
+  };
+  let value = match text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+    Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+    None => text.parse::<i64>().ok()?,
+  };
+  Some(if negative { -value } else { value })
+}
+fn trailing_numbers(text: &str) -> Vec<u16> {
+  let token = text.split_whitespace().last().unwrap_or_default();
+  let token = token.strip_prefix("0x").unwrap_or(token);
+  token.parse::<u16>().ok().into_iter().chain(u16::from_str_radix(token, 16).ok()).collect()
+}
+fn usage_page_names() -> &'static HashMap<String, u16> {
+  static NAMES: OnceLock<HashMap<String, u16>> = OnceLock::new();
+  NAMES.get_or_init(|| {
+    let mut names = HashMap::new();
+    for page in 0..=u16::MAX {
+      if let Ok(usage_page) = UsagePage::try_from(page) && !matches!(usage_page, UsagePage::ReservedUsagePage(_) | UsagePage::VendorDefinedPage(_)) {
+        names.entry(normalize(&usage_page.name())).or_insert(page);
+      };
+    };
+    names
+  })
+}
+fn usage_id_by_name(page: u16, name: &str) -> Option<u16> {
+  static NAMES: OnceLock<Mutex<HashMap<u16, HashMap<String, u16>>>> = OnceLock::new();
+  let mut pages = NAMES.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner);
+  pages.entry(page).or_insert_with(|| {
+    let mut names = HashMap::new();
+    if UsagePage::try_from(page).is_ok() {
+      for id in 0..=u16::MAX {
+        if let Ok(usage) = Usage::new_from_page_and_id(page, id) {
+          names.entry(normalize(&usage.name())).or_insert(id);
+        };
+      };
+    };
+    names
+  }).get(name).copied()
+}
+fn parse_usage_page(text: &str) -> Option<u16> {
+  if let Some(page) = parse_number(text) {
+    return u16::try_from(page).ok();
+  };
+  let name = normalize(text);
+  let matches = |page: &u16| UsagePage::try_from(*page).is_ok_and(|page| normalize(&page.name()) == name);
+  if let Some(page) = trailing_numbers(text).into_iter().find(matches) {
+    return Some(page);
+  };
+  usage_page_names().get(&name).copied()
+}
+fn parse_usage_id(page: u16, text: &str) -> Option<u16> {
+  let name = normalize(text);
+  let matches = |id: &u16| Usage::new_from_page_and_id(page, *id).is_ok_and(|usage| normalize(&usage.name()) == name);
+  trailing_numbers(text).into_iter().find(matches).or_else(|| usage_id_by_name(page, &name))
+}
+fn parse_usage(text: &str, usage_page: Option<u16>) -> Option<UsageSpecifier> {
+  if let Some(usage) = parse_number(text) {
+    return match usage {
+      0..=0xFFFF => Some(UsageSpecifier::Usage(usage as u16)),
+      0x10000..=0xFFFF_FFFF => Some(UsageSpecifier::ExtendedUsage(usage as u32)),
+      _ => None,
+    };
+  };
+  if let Some(id) = usage_page.and_then(|page| parse_usage_id(page, text)) {
+    return Some(UsageSpecifier::Usage(id));
+  };
+  let (page, id) = text.split_once(": ")?;
+  let page = parse_usage_page(page)?;
+  let id = parse_usage_number_or_name(page, id)?;
+  Some(UsageSpecifier::ExtendedUsage(((page as u32) << 16) | id as u32))
+}
+fn parse_usage_number_or_name(page: u16, text: &str) -> Option<u16> {
+  match parse_number(text) {
+    Some(id) => u16::try_from(id).ok(),
+    None => parse_usage_id(page, text),
+  }
+}
+fn parse_collection_type(text: &str) -> Option<CollectionType> {
+  if let Some(value) = parse_number(text) {
+    return collection_type_from_value(u8::try_from(value).ok()?);
+  };
+  let name = normalize(text);
+  if let Some(value) = name.strip_prefix("vendordefined") {
+    return match collection_type_from_value(u8::try_from(parse_number(value)?).ok()?)? {
+      CollectionType::VendorDefined(n) => Some(CollectionType::VendorDefined(n)),
+      _ => None,
+    };
+  };
+  Some(match name.as_str() {
+    "physical" => CollectionType::Physical,
+    "application" => CollectionType::Application,
+    "logical" => CollectionType::Logical,
+    "report" => CollectionType::Report,
+    "namedarray" => CollectionType::NamedArray,
+    "usageswitch" => CollectionType::UsageSwitch,
+    "usagemodifier" => CollectionType::UsageModifier,
+    _ => return None,
+  })
+}
+fn collection_type_from_value(value: u8) -> Option<CollectionType> {
+  Some(match value {
+    0 => CollectionType::Physical,
+    1 => CollectionType::Application,
+    2 => CollectionType::Logical,
+    3 => CollectionType::Report,
+    4 => CollectionType::NamedArray,
+    5 => CollectionType::UsageSwitch,
+    6 => CollectionType::UsageModifier,
+    n if n >= 0x80 => CollectionType::VendorDefined(n),
+    _ => return None,
+  })
+}
+fn parse_data(text: &str) -> Option<(u8, Vec<u8>)> {
+  let mut tokens = text.split_whitespace();
+  let tag = u8::try_from(parse_number(tokens.next()?)?).ok()?;
+  let data = tokens.map(|token| u8::from_str_radix(token, 16).ok()).collect::<Option<Vec<u8>>>()?;
+  Some((tag, data))
+}
+fn parse_main_item(name: &str, text: &str) -> Option<DescriptorItem> {
+  let mut constant = ReportConstantFlag::Data;
+  let mut layout = ReportLayoutFlag::Array;
+  let mut relative = ReportRelativeFlag::Absolute;
+  let mut wrap = ReportWrapFlag::NoWrap;
+  let mut linear = ReportLinearFlag::Linear;
+  let mut preferred_state = ReportPreferredStateFlag::PreferredState;
+  let mut null_state = ReportNullStateFlag::NoNullPosition;
+  let mut volatile = None;
+  let mut buffered_bytes = ReportBufferedBytesFlag::BitField;
+  for flag in text.split(',').map(normalize).filter(|flag| !flag.is_empty()) {
+    match flag.as_str() {
+      "data" | "dat" => constant = ReportConstantFlag::Data,
+      "constant" | "const" | "cnst" => constant = ReportConstantFlag::Constant,
+      "array" | "ary" => layout = ReportLayoutFlag::Array,
+      "variable" | "var" => layout = ReportLayoutFlag::Variable,
+      "absolute" | "abs" => relative = ReportRelativeFlag::Absolute,
+      "relative" | "rel" => relative = ReportRelativeFlag::Relative,
+      "nowrap" => wrap = ReportWrapFlag::NoWrap,
+      "wrap" => wrap = ReportWrapFlag::Wrap,
+      "linear" | "lin" => linear = ReportLinearFlag::Linear,
+      "nonlinear" | "nonlin" => linear = ReportLinearFlag::NonLinear,
+      "preferredstate" | "prefstate" | "pref" => preferred_state = ReportPreferredStateFlag::PreferredState,
+      "nopreferred" | "nopreferredstate" | "nopref" => preferred_state = ReportPreferredStateFlag::NoPreferred,
+      "nonullposition" | "nonull" => null_state = ReportNullStateFlag::NoNullPosition,
+      "nullstate" | "null" => null_state = ReportNullStateFlag::NullState,
+      "nonvolatile" | "nonvol" => volatile = Some(ReportVolatileFlag::NonVolatile),
+      "volatile" | "vol" => volatile = Some(ReportVolatileFlag::Volatile),
+      "bitfield" => buffered_bytes = ReportBufferedBytesFlag::BitField,
+      "bufferedbytes" | "buf" | "buff" => buffered_bytes = ReportBufferedBytesFlag::BufferedBytes,
+      _ => return None,
+    };
+  };
+  Some(match name {
+    "input" if volatile.is_none() => DescriptorItem::Input { constant, layout, relative, wrap, linear, preferred_state, null_state, buffered_bytes },
+    "output" => DescriptorItem::Output { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile: volatile.unwrap_or(ReportVolatileFlag::NonVolatile), buffered_bytes },
+    "feature" => DescriptorItem::Feature { constant, layout, relative, wrap, linear, preferred_state, null_state, volatile: volatile.unwrap_or(ReportVolatileFlag::NonVolatile), buffered_bytes },
+    _ => return None,
+  })
+}
+fn strip_bytes(line: &str) -> &str {
+  let mut line = line.trim_start();
+  while let Some((token, rest)) = line.split_once(char::is_whitespace) {
+    if token.len() != 2 || !token.chars().all(|character| character.is_ascii_hexdigit()) {
+      break;
+    };
+    line = rest.trim_start();
+  };
+  if line.len() == 2 && line.chars().all(|character| character.is_ascii_hexdigit()) {
+    return "";
+  };
+  line
+}
+pub fn items_from_text(text: &str) -> Result<Vec<DescriptorItem>> {
+  let mut items = vec![];
+  let mut usage_page: Option<u16> = None;
+  let mut stack: Vec<Option<u16>> = vec![];
+  for (index, line) in text.lines().enumerate() {
+    let line_number = index+1;
+    let line = match line.find("//") {
+      Some(comment) => &line[..comment],
+      None => line,
+    };
+    let line = strip_bytes(line).trim();
+    if line.is_empty() {
+      continue;
+    };
+    let (name, argument) = match line.find('(') {
+      Some(open) => {
+        let Some(close) = line.rfind(')').filter(|close| *close > open && line[close+1..].trim().is_empty()) else {
+          return Err(Error::InvalidSyntax { line: line_number });
+        };
+        (normalize(&line[..open]), Some(line[open+1..close].trim()))
+      },
+      None => (normalize(line), None),
+    };
+    let invalid = || Error::InvalidArgument { line: line_number };
+    let number = |bounds: RangeInclusive<i64>| argument.and_then(parse_number).filter(|number| bounds.contains(number)).ok_or_else(invalid);
+    let item = match (name.as_str(), argument) {
+      ("input" | "output" | "feature", Some(argument)) => parse_main_item(&name, argument).ok_or_else(invalid)?,
+      ("collection", Some(argument)) => DescriptorItem::Collection(parse_collection_type(argument).ok_or_else(invalid)?),
+      ("endcollection", None) => DescriptorItem::EndCollection,
+      ("usagepage", Some(argument)) => {
+        let page = parse_usage_page(argument).ok_or_else(invalid)?;
+        usage_page = Some(page);
+        DescriptorItem::UsagePage(page)
+      },
+      ("logicalminimum", Some(_)) => DescriptorItem::LogicalMinimum(number(i32::MIN as i64..=u32::MAX as i64)? as i32),
+      ("logicalmaximum", Some(_)) => DescriptorItem::LogicalMaximum(number(i32::MIN as i64..=u32::MAX as i64)? as i32),
+      ("physicalminimum", Some(_)) => DescriptorItem::PhysicalMinimum(number(i32::MIN as i64..=u32::MAX as i64)? as i32),
+      ("physicalmaximum", Some(_)) => DescriptorItem::PhysicalMaximum(number(i32::MIN as i64..=u32::MAX as i64)? as i32),
+      ("unitexponent", Some(_)) => DescriptorItem::UnitExponent(number(-8..=7)? as i8),
+      ("unit", Some(argument)) => DescriptorItem::Unit(match parse_number(argument) {
+        Some(_) => Unit::from_raw(number(0..=u32::MAX as i64)? as u32),
+        None => argument.parse::<Unit>().map_err(|_| invalid())?,
+      }),
+      ("reportsize", Some(_)) => DescriptorItem::ReportSize(number(0..=u32::MAX as i64)? as u32),
+      ("reportid", Some(_)) => DescriptorItem::ReportId(number(0..=u8::MAX as i64)? as u8),
+      ("reportcount", Some(_)) => DescriptorItem::ReportCount(number(0..=u32::MAX as i64)? as u32),
+      ("push", None) => {
+        stack.push(usage_page);
+        DescriptorItem::Push
+      },
+      ("pop", None) => {
+        if let Some(previous) = stack.pop() {
+          usage_page = previous;
+        };
+        DescriptorItem::Pop
+      },
+      ("usage", Some(argument)) => DescriptorItem::Usage(parse_usage(argument, usage_page).ok_or_else(invalid)?),
+      ("usageminimum", Some(argument)) => DescriptorItem::UsageMinimum(parse_usage(argument, usage_page).ok_or_else(invalid)?),
+      ("usagemaximum", Some(argument)) => DescriptorItem::UsageMaximum(parse_usage(argument, usage_page).ok_or_else(invalid)?),
+      ("designatorindex", Some(_)) => DescriptorItem::DesignatorIndex(number(0..=u32::MAX as i64)? as u32),
+      ("designatorminimum", Some(_)) => DescriptorItem::DesignatorMinimum(number(0..=u32::MAX as i64)? as u32),
+      ("designatormaximum", Some(_)) => DescriptorItem::DesignatorMaximum(number(0..=u32::MAX as i64)? as u32),
+      ("stringindex", Some(_)) => DescriptorItem::StringIndex(number(0..=u32::MAX as i64)? as u32),
+      ("stringminimum", Some(_)) => DescriptorItem::StringMinimum(number(0..=u32::MAX as i64)? as u32),
+      ("stringmaximum", Some(_)) => DescriptorItem::StringMaximum(number(0..=u32::MAX as i64)? as u32),
+      ("delimiter", Some(argument)) => match normalize(argument).as_str() {
+        "open" => DescriptorItem::Delimiter(DelimiterFlag::Open),
+        "close" => DescriptorItem::Delimiter(DelimiterFlag::Close),
+        _ => return Err(invalid()),
+      },
+      ("longitem", Some(argument)) => {
+        let (tag, data) = parse_data(argument).filter(|(_, data)| data.len() <= 255).ok_or_else(invalid)?;
+        DescriptorItem::Long { tag, data }
+      },
+      (name, Some(argument)) if name.starts_with("reserved") && name.ends_with("item") => {
+        let kind = match &name["reserved".len()..name.len()-"item".len()] {
+          "main" => ItemType::Main,
+          "global" => ItemType::Global,
+          "local" => ItemType::Local,
+          "reserved" => ItemType::Reserved,
+          _ => return Err(Error::UnknownItemName { line: line_number }),
+        };
+        let (tag, data) = parse_data(argument).filter(|(tag, data)| kind.is_reserved_tag(*tag, data.len()) && matches!(data.len(), 0 | 1 | 2 | 4)).ok_or_else(invalid)?;
+        DescriptorItem::Reserved { kind, tag, data }
+      },
+      _ => return Err(Error::UnknownItemName { line: line_number }),
+    };
+    items.push(item);
+  };
+  Ok(items)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::display::display_items;
+  const DATA: [u8; 50] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x85, 0x02,
+    0x05, 0x09, 0x19, 0x01, 0x29, 0x03, 0x15, 0x00,
+    0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02,
+    0xA4, 0x66, 0x11, 0xF0, 0x55, 0x0D, 0xB4, 0xA9,
+    0x01, 0x09, 0x30, 0x09, 0x31, 0xA9, 0x00, 0xFE,
+    0x01, 0x42, 0x07, 0xF5, 0x01, 0x39, 0x04, 0x79,
+    0x02, 0xC0,
+  ];
+  fn items() -> Vec<DescriptorItem> {
+    items_from_bytes(&DATA).collect::<Result<Vec<_>>>().unwrap()
+  }
+  #[test]
+  fn text_round_trip() {
+    let items = items();
+    let text = items_to_text(&items);
+    assert_eq!(items_from_text(&text), Ok(items.clone()));
+    assert_eq!(items_to_text(&items_from_text(&text).unwrap()), text);
+  }
+  #[test]
+  fn display_round_trip() {
+    let items = items();
+    assert_eq!(items_from_text(&display_items(&items).to_string()), Ok(items));
+  }
+  #[test]
+  fn resolves_usage_names_from_lookup_tables() {
+    let text = "Usage Page (Consumer)\nUsage (Volume Increment)\nUsage (Generic Desktop: Wheel)\n".repeat(500);
+    let items = items_from_text(&text).unwrap();
+    assert_eq!(items.len(), 1500);
+    assert_eq!(items[..3], [
+      DescriptorItem::UsagePage(0x0C),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0xE9)),
+      DescriptorItem::Usage(UsageSpecifier::ExtendedUsage(0x0001_0038)),
+    ]);
+    assert_eq!(items_from_text("Usage Page (Consumer)\nUsage (Not A Usage)"), Err(Error::InvalidArgument { line: 2 }));
+  }
+  #[test]
+  fn parses_hand_written_text() {
+    let text = "
+      usage_page(generic desktop) // comment
+      USAGE (X)
+      05 09  Usage Page (0x09)
+      usage minimum(1)
+      Usage Maximum (Button 3)
+      Logical Minimum (-0x7F)
+      Unit Exponent (-2)
+    ";
+    assert_eq!(items_from_text(text), Ok(vec![
+      DescriptorItem::UsagePage(0x01),
+      DescriptorItem::Usage(UsageSpecifier::Usage(0x30)),
+      DescriptorItem::UsagePage(0x09),
+      DescriptorItem::UsageMinimum(UsageSpecifier::Usage(0x01)),
+      DescriptorItem::UsageMaximum(UsageSpecifier::Usage(0x03)),
+      DescriptorItem::LogicalMinimum(-127),
+      DescriptorItem::UnitExponent(-2),
+    ]));
+  }
+  #[test]
+  fn rejects_invalid_text() {
+    assert_eq!(items_from_text("Usage Page (1)\nUsage Page (1"), Err(Error::InvalidSyntax { line: 2 }));
+    assert_eq!(items_from_text("Usage Pages (1)"), Err(Error::UnknownItemName { line: 1 }));
+    assert_eq!(items_from_text("Report Size (-1)"), Err(Error::InvalidArgument { line: 1 }));
+    assert_eq!(items_from_text(&format!("Long Item (0x42{})", " 00".repeat(256))), Err(Error::InvalidArgument { line: 1 }));
+    assert_eq!(items_from_text("Reserved Global Item (0x0f 01 02 03)"), Err(Error::InvalidArgument { line: 1 }));
+    assert_eq!(items_from_text("Reserved Main Item (0x08 02)"), Err(Error::InvalidArgument { line: 1 }));
+  }
+}
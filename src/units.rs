@@ -0,0 +1,286 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::Error;
+pub const fn normalize_unit_exponent(exponent: i8) -> i8 {
+  match exponent {
+    8..=15 => exponent-16,
+    exponent => exponent,
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum UnitSystem {
+  None,
+  SiLinear,
+  SiRotation,
+  EnglishLinear,
+  EnglishRotation,
+  Reserved(u8),
+  VendorDefined,
+}
+impl UnitSystem {
+  pub const fn from_value(value: u8) -> Self {
+    match value & 0xF {
+      0 => UnitSystem::None,
+      1 => UnitSystem::SiLinear,
+      2 => UnitSystem::SiRotation,
+      3 => UnitSystem::EnglishLinear,
+      4 => UnitSystem::EnglishRotation,
+      0xF => UnitSystem::VendorDefined,
+      value => UnitSystem::Reserved(value),
+    }
+  }
+  pub const fn value(self) -> u8 {
+    match self {
+      UnitSystem::None => 0,
+      UnitSystem::SiLinear => 1,
+      UnitSystem::SiRotation => 2,
+      UnitSystem::EnglishLinear => 3,
+      UnitSystem::EnglishRotation => 4,
+      UnitSystem::Reserved(value) => value & 0xF,
+      UnitSystem::VendorDefined => 0xF,
+    }
+  }
+  pub fn name(self) -> String {
+    match self {
+      UnitSystem::None => "None".to_string(),
+      UnitSystem::SiLinear => "SI Linear".to_string(),
+      UnitSystem::SiRotation => "SI Rotation".to_string(),
+      UnitSystem::EnglishLinear => "English Linear".to_string(),
+      UnitSystem::EnglishRotation => "English Rotation".to_string(),
+      UnitSystem::Reserved(value) => format!("Reserved {value:#x}"),
+      UnitSystem::VendorDefined => "Vendor Defined".to_string(),
+    }
+  }
+  fn symbols(self) -> Option<[&'static str; 6]> {
+    match self {
+      UnitSystem::SiLinear => Some(["cm", "g", "s", "K", "A", "cd"]),
+      UnitSystem::SiRotation => Some(["rad", "g", "s", "K", "A", "cd"]),
+      UnitSystem::EnglishLinear => Some(["in", "slug", "s", "°F", "A", "cd"]),
+      UnitSystem::EnglishRotation => Some(["deg", "slug", "s", "°F", "A", "cd"]),
+      _ => None,
+    }
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Unit(u32);
+impl Unit {
+  pub const NONE: Unit = Unit(0);
+  pub const fn from_raw(raw: u32) -> Self {
+    Unit(raw)
+  }
+  pub const fn raw(self) -> u32 {
+    self.0
+  }
+  pub const fn new(system: UnitSystem, length: i8, mass: i8, time: i8, temperature: i8, current: i8, luminous_intensity: i8) -> Self {
+    let exponents = [length, mass, time, temperature, current, luminous_intensity];
+    let mut raw = system.value() as u32;
+    let mut index = 0;
+    while index < exponents.len() {
+      raw |= ((exponents[index] as u8 & 0xF) as u32) << (4*(index+1));
+      index += 1;
+    };
+    Unit(raw)
+  }
+  pub const fn system(self) -> UnitSystem {
+    UnitSystem::from_value(self.0 as u8)
+  }
+  const fn exponent(self, index: u32) -> i8 {
+    let nibble = ((self.0 >> (4*(index+1))) & 0xF) as i8;
+    if nibble >= 8 { nibble-16 } else { nibble }
+  }
+  pub const fn length(self) -> i8 {
+    self.exponent(0)
+  }
+  pub const fn mass(self) -> i8 {
+    self.exponent(1)
+  }
+  pub const fn time(self) -> i8 {
+    self.exponent(2)
+  }
+  pub const fn temperature(self) -> i8 {
+    self.exponent(3)
+  }
+  pub const fn current(self) -> i8 {
+    self.exponent(4)
+  }
+  pub const fn luminous_intensity(self) -> i8 {
+    self.exponent(5)
+  }
+  pub const fn exponents(self) -> [i8; 6] {
+    [self.length(), self.mass(), self.time(), self.temperature(), self.current(), self.luminous_intensity()]
+  }
+  pub const fn centimeter() -> Self {
+    Unit::new(UnitSystem::SiLinear, 1, 0, 0, 0, 0, 0)
+  }
+  pub const fn inch() -> Self {
+    Unit::new(UnitSystem::EnglishLinear, 1, 0, 0, 0, 0, 0)
+  }
+  pub const fn radian() -> Self {
+    Unit::new(UnitSystem::SiRotation, 1, 0, 0, 0, 0, 0)
+  }
+  pub const fn degree() -> Self {
+    Unit::new(UnitSystem::EnglishRotation, 1, 0, 0, 0, 0, 0)
+  }
+  pub const fn second() -> Self {
+    Unit::new(UnitSystem::SiLinear, 0, 0, 1, 0, 0, 0)
+  }
+  pub const fn gram() -> Self {
+    Unit::new(UnitSystem::SiLinear, 0, 1, 0, 0, 0, 0)
+  }
+  pub const fn kelvin() -> Self {
+    Unit::new(UnitSystem::SiLinear, 0, 0, 0, 1, 0, 0)
+  }
+  pub const fn ampere() -> Self {
+    Unit::new(UnitSystem::SiLinear, 0, 0, 0, 0, 1, 0)
+  }
+  pub const fn candela() -> Self {
+    Unit::new(UnitSystem::SiLinear, 0, 0, 0, 0, 0, 1)
+  }
+  pub const fn velocity() -> Self {
+    Unit::new(UnitSystem::SiLinear, 1, 0, -1, 0, 0, 0)
+  }
+  pub const fn acceleration() -> Self {
+    Unit::new(UnitSystem::SiLinear, 1, 0, -2, 0, 0, 0)
+  }
+  pub const fn angular_velocity() -> Self {
+    Unit::new(UnitSystem::EnglishRotation, 1, 0, -1, 0, 0, 0)
+  }
+  pub fn symbol(self) -> Option<String> {
+    let symbols = self.system().symbols()?;
+    if self.0 >> 28 != 0 {
+      return None;
+    };
+    let terms = symbols.iter().zip(self.exponents()).filter(|(_, exponent)| *exponent != 0).map(|(symbol, exponent)| match exponent {
+      1 => symbol.to_string(),
+      exponent => format!("{symbol}{}", superscript(exponent)),
+    }).collect::<Vec<String>>();
+    if terms.is_empty() {
+      None
+    }
+    else {
+      Some(terms.join("·"))
+    }
+  }
+}
+fn superscript(value: i8) -> String {
+  value.to_string().chars().map(|character| match character {
+    '-' => '⁻',
+    '0' => '⁰',
+    '1' => '¹',
+    '2' => '²',
+    '3' => '³',
+    '4' => '⁴',
+    '5' => '⁵',
+    '6' => '⁶',
+    '7' => '⁷',
+    '8' => '⁸',
+    _ => '⁹',
+  }).collect()
+}
+fn from_superscript(text: &str) -> Option<i8> {
+  if let Some(text) = text.strip_prefix('^') {
+    return text.parse().ok();
+  };
+  let digits = text.chars().map(|character| match character {
+    '⁻' => Some('-'),
+    '⁰' => Some('0'),
+    '¹' => Some('1'),
+    '²' => Some('2'),
+    '³' => Some('3'),
+    '⁴' => Some('4'),
+    '⁵' => Some('5'),
+    '⁶' => Some('6'),
+    '⁷' => Some('7'),
+    '⁸' => Some('8'),
+    '⁹' => Some('9'),
+    _ => None,
+  }).collect::<Option<String>>()?;
+  digits.parse().ok()
+}
+impl fmt::Display for Unit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.symbol() {
+      Some(symbol) => write!(f, "{symbol}"),
+      None if self.0 == 0 => write!(f, "None"),
+      None => write!(f, "{:#x}", self.0),
+    }
+  }
+}
+impl FromStr for Unit {
+  type Err = Error;
+  fn from_str(text: &str) -> Result<Self, Error> {
+    let invalid = || Error::InvalidUnit { unit: text.to_string() };
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("none") {
+      return Ok(Unit::NONE);
+    };
+    if let Some(raw) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+      return u32::from_str_radix(raw, 16).map(Unit).map_err(|_| invalid());
+    };
+    let (system, terms) = match text.split_once(':') {
+      Some((system, terms)) => {
+        let system = system.trim().to_lowercase().replace([' ', '_', '-'], "");
+        let system = [UnitSystem::SiLinear, UnitSystem::SiRotation, UnitSystem::EnglishLinear, UnitSystem::EnglishRotation].into_iter().find(|candidate| candidate.name().to_lowercase().replace(' ', "") == system).ok_or_else(invalid)?;
+        (Some(system), terms)
+      },
+      None => (None, text),
+    };
+    let mut parsed = vec![];
+    for term in terms.split(['·', '*', ' ']).filter(|term| !term.is_empty()) {
+      let split = term.find(|character: char| "^⁻⁰¹²³⁴⁵⁶⁷⁸⁹".contains(character)).unwrap_or(term.len());
+      let exponent = if split == term.len() { 1 } else { from_superscript(&term[split..]).ok_or_else(invalid)? };
+      if !(-8..=7).contains(&exponent) {
+        return Err(invalid());
+      };
+      parsed.push((&term[..split], exponent));
+    };
+    if parsed.is_empty() {
+      return Err(invalid());
+    };
+    let candidates = match system {
+      Some(system) => vec![system],
+      None => vec![UnitSystem::SiLinear, UnitSystem::SiRotation, UnitSystem::EnglishLinear, UnitSystem::EnglishRotation],
+    };
+    'systems: for system in candidates {
+      let symbols = system.symbols().unwrap();
+      let mut exponents = [0i8; 6];
+      for (symbol, exponent) in &parsed {
+        let Some(index) = symbols.iter().position(|candidate| candidate == symbol) else {
+          continue 'systems;
+        };
+        exponents[index] = exponents[index].checked_add(*exponent).filter(|exponent| (-8..=7).contains(exponent)).ok_or_else(invalid)?;
+      };
+      return Ok(Unit::new(system, exponents[0], exponents[1], exponents[2], exponents[3], exponents[4], exponents[5]));
+    };
+    Err(invalid())
+  }
+}
+impl From<u32> for Unit {
+  fn from(raw: u32) -> Self {
+    Unit(raw)
+  }
+}
+impl From<Unit> for u32 {
+  fn from(unit: Unit) -> Self {
+    unit.0
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn display_round_trip() {
+    for (unit, text) in [(Unit::centimeter(), "cm"), (Unit::acceleration(), "cm·s⁻²"), (Unit::degree(), "deg"), (Unit::NONE, "None"), (Unit::from_raw(0xE12F), "0xe12f"), (Unit::from_raw(0x15), "0x15")] {
+      assert_eq!(unit.to_string(), text);
+      assert_eq!(text.parse::<Unit>(), Ok(unit));
+    };
+    assert_eq!("0xE12F".parse::<Unit>(), Ok(Unit::from_raw(0xE12F)));
+    assert_eq!("cm^7 cm^-7".parse::<Unit>(), Ok(Unit::new(UnitSystem::SiLinear, 0, 0, 0, 0, 0, 0)));
+  }
+  #[test]
+  fn rejects_out_of_range_exponents() {
+    for text in ["cm^7 cm^7", "cm^8", "s⁻⁸ s⁻¹", &["cm"; 200].join(" "), "0xG", "0x1_0000_0000"] {
+      assert_eq!(text.parse::<Unit>(), Err(Error::InvalidUnit { unit: text.to_string() }));
+    };
+  }
+}
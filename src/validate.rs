@@ -0,0 +1,310 @@
+use std::fmt;
+use crate::descriptor_items::*;
+use crate::descriptors::ReportType;
+use crate::error::Result;
+use crate::macros::hut;
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Severity {
+  Warning,
+  Error,
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DiagnosticKind {
+  UnmatchedEndCollection,
+  UnclosedCollection,
+  MissingReportSize,
+  MissingReportCount,
+  InvertedLogicalRange { minimum: i32, maximum: i32 },
+  ReportIdZero,
+  UnnumberedReport { ty: ReportType },
+  UnalignedReport { ty: ReportType, id: Option<u8>, bits: usize },
+  UnmatchedDelimiter,
+  NestedDelimiter,
+  UnclosedDelimiter,
+  UnmatchedPop,
+  MissingUsagePage,
+  UnknownUsage { usage: u32 },
+  UsageRangeAcrossPages { minimum: u32, maximum: u32 },
+}
+impl DiagnosticKind {
+  pub fn severity(&self) -> Severity {
+    match self {
+      DiagnosticKind::UnknownUsage { .. } => Severity::Warning,
+      _ => Severity::Error,
+    }
+  }
+}
+impl fmt::Display for DiagnosticKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DiagnosticKind::UnmatchedEndCollection => write!(f, "End Collection without a matching Collection"),
+      DiagnosticKind::UnclosedCollection => write!(f, "Collection without a matching End Collection"),
+      DiagnosticKind::MissingReportSize => write!(f, "Main item without a Report Size"),
+      DiagnosticKind::MissingReportCount => write!(f, "Main item without a Report Count"),
+      DiagnosticKind::InvertedLogicalRange { minimum, maximum } => write!(f, "Logical Minimum {minimum} is greater than Logical Maximum {maximum}"),
+      DiagnosticKind::ReportIdZero => write!(f, "Report ID 0 is reserved"),
+      DiagnosticKind::UnnumberedReport { ty } => write!(f, "{ty:?} item without a Report ID in a descriptor that uses Report IDs"),
+      DiagnosticKind::UnalignedReport { ty, id: Some(id), bits } => write!(f, "{ty:?} report {id} is {bits} bits long, which is not a whole number of bytes"),
+      DiagnosticKind::UnalignedReport { ty, id: None, bits } => write!(f, "Unnumbered {ty:?} report is {bits} bits long, which is not a whole number of bytes"),
+      DiagnosticKind::UnmatchedDelimiter => write!(f, "Delimiter Close without a matching Delimiter Open"),
+      DiagnosticKind::NestedDelimiter => write!(f, "Delimiter Open inside another delimiter set"),
+      DiagnosticKind::UnclosedDelimiter => write!(f, "Delimiter Open without a matching Delimiter Close"),
+      DiagnosticKind::UnmatchedPop => write!(f, "Pop without a matching Push"),
+      DiagnosticKind::MissingUsagePage => write!(f, "Usage without a Usage Page"),
+      DiagnosticKind::UnknownUsage { usage } => write!(f, "Usage {:#06x} is not defined on usage page {:#06x}", usage & 0xFFFF, usage >> 16),
+      DiagnosticKind::UsageRangeAcrossPages { minimum, maximum } => write!(f, "Usage Minimum {minimum:#010x} and Usage Maximum {maximum:#010x} are on different usage pages"),
+    }
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub index: usize,
+  pub offset: usize,
+  pub kind: DiagnosticKind,
+}
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?} at item {} (byte {}): {}", self.severity, self.index, self.offset, self.kind)
+  }
+}
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+struct GlobalState {
+  usage_page: Option<u16>,
+  logical_minimum: Option<i32>,
+  logical_maximum: Option<i32>,
+  report_size: Option<u32>,
+  report_id: Option<u8>,
+  report_count: Option<u32>,
+}
+struct Validator<'a> {
+  offsets: &'a [usize],
+  diagnostics: Vec<Diagnostic>,
+}
+impl Validator<'_> {
+  fn report(&mut self, index: usize, kind: DiagnosticKind) {
+    let offset = self.offsets[index];
+    self.diagnostics.push(Diagnostic { severity: kind.severity(), index, offset, kind });
+  }
+  fn resolve_usage(&mut self, index: usize, usage: UsageSpecifier, state: &GlobalState) -> Option<u32> {
+    match usage {
+      UsageSpecifier::Usage(usage) => {
+        let Some(page) = state.usage_page else {
+          self.report(index, DiagnosticKind::MissingUsagePage);
+          return None;
+        };
+        Some(((page as u32) << 16) | usage as u32)
+      },
+      UsageSpecifier::ExtendedUsage(usage) => Some(usage),
+    }
+  }
+  fn check_usage(&mut self, index: usize, usage: u32) {
+    if usage & 0xFFFF != 0 && usage >> 16 < 0xFF00 && hut::Usage::try_from(usage).is_err() {
+      self.report(index, DiagnosticKind::UnknownUsage { usage });
+    };
+  }
+  fn run(&mut self, items: &[DescriptorItem]) {
+    let numbered = items.iter().any(|item| matches!(item, DescriptorItem::ReportId(_)));
+    let mut state = GlobalState::default();
+    let mut stack: Vec<GlobalState> = vec![];
+    let mut collections: Vec<usize> = vec![];
+    let mut delimiter: Option<usize> = None;
+    let mut usage_minimum: Option<u32> = None;
+    let mut reports: Vec<(ReportType, Option<u8>, usize, usize)> = vec![];
+    for (index, item) in items.iter().enumerate() {
+      match *item {
+        DescriptorItem::Input { .. } | DescriptorItem::Output { .. } | DescriptorItem::Feature { .. } => {
+          let ty = match item {
+            DescriptorItem::Input { .. } => ReportType::Input,
+            DescriptorItem::Output { .. } => ReportType::Output,
+            _ => ReportType::Feature,
+          };
+          if state.report_size.is_none() {
+            self.report(index, DiagnosticKind::MissingReportSize);
+          };
+          if state.report_count.is_none() {
+            self.report(index, DiagnosticKind::MissingReportCount);
+          };
+          if let (Some(minimum), Some(maximum)) = (state.logical_minimum, state.logical_maximum) && minimum > maximum {
+            self.report(index, DiagnosticKind::InvertedLogicalRange { minimum, maximum });
+          };
+          if numbered && state.report_id.is_none() {
+            self.report(index, DiagnosticKind::UnnumberedReport { ty });
+          };
+          let bits = state.report_size.unwrap_or(0) as usize*state.report_count.unwrap_or(0) as usize;
+          match reports.iter_mut().find(|(report_ty, id, _, _)| *report_ty == ty && *id == state.report_id) {
+            Some((_, _, report_bits, last)) => {
+              *report_bits += bits;
+              *last = index;
+            },
+            None => reports.push((ty, state.report_id, bits, index)),
+          };
+          if let Some(open) = delimiter.take() {
+            self.report(open, DiagnosticKind::UnclosedDelimiter);
+          };
+          usage_minimum = None;
+        },
+        DescriptorItem::Collection(_) => {
+          collections.push(index);
+          if let Some(open) = delimiter.take() {
+            self.report(open, DiagnosticKind::UnclosedDelimiter);
+          };
+          usage_minimum = None;
+        },
+        DescriptorItem::EndCollection => {
+          if collections.pop().is_none() {
+            self.report(index, DiagnosticKind::UnmatchedEndCollection);
+          };
+          usage_minimum = None;
+        },
+        DescriptorItem::UsagePage(page) => state.usage_page = Some(page),
+        DescriptorItem::LogicalMinimum(minimum) => state.logical_minimum = Some(minimum),
+        DescriptorItem::LogicalMaximum(maximum) => state.logical_maximum = Some(maximum),
+        DescriptorItem::ReportSize(size) => state.report_size = Some(size),
+        DescriptorItem::ReportCount(count) => state.report_count = Some(count),
+        DescriptorItem::ReportId(id) => {
+          if id == 0 {
+            self.report(index, DiagnosticKind::ReportIdZero);
+          };
+          state.report_id = Some(id);
+        },
+        DescriptorItem::Push => stack.push(state.clone()),
+        DescriptorItem::Pop => match stack.pop() {
+          Some(previous) => state = previous,
+          None => self.report(index, DiagnosticKind::UnmatchedPop),
+        },
+        DescriptorItem::Usage(usage) => {
+          if let Some(usage) = self.resolve_usage(index, usage, &state) {
+            self.check_usage(index, usage);
+          };
+        },
+        DescriptorItem::UsageMinimum(usage) => {
+          usage_minimum = self.resolve_usage(index, usage, &state);
+          if let Some(usage) = usage_minimum {
+            self.check_usage(index, usage);
+          };
+        },
+        DescriptorItem::UsageMaximum(usage) => {
+          if let Some(maximum) = self.resolve_usage(index, usage, &state) {
+            self.check_usage(index, maximum);
+            if let Some(minimum) = usage_minimum.take() && minimum >> 16 != maximum >> 16 {
+              self.report(index, DiagnosticKind::UsageRangeAcrossPages { minimum, maximum });
+            };
+          };
+        },
+        DescriptorItem::Delimiter(DelimiterFlag::Open) => {
+          if delimiter.is_some() {
+            self.report(index, DiagnosticKind::NestedDelimiter);
+          };
+          delimiter = Some(index);
+        },
+        DescriptorItem::Delimiter(DelimiterFlag::Close) => match delimiter.take() {
+          Some(_) => {},
+          None => self.report(index, DiagnosticKind::UnmatchedDelimiter),
+        },
+        _ => {},
+      };
+    };
+    if let Some(open) = delimiter {
+      self.report(open, DiagnosticKind::UnclosedDelimiter);
+    };
+    for index in collections {
+      self.report(index, DiagnosticKind::UnclosedCollection);
+    };
+    for (ty, id, bits, last) in reports {
+      if bits % 8 != 0 {
+        self.report(last, DiagnosticKind::UnalignedReport { ty, id, bits });
+      };
+    };
+  }
+}
+fn validate_at(items: &[DescriptorItem], offsets: &[usize]) -> Vec<Diagnostic> {
+  let mut validator = Validator { offsets, diagnostics: vec![] };
+  validator.run(items);
+  validator.diagnostics
+}
+pub fn validate(items: &[DescriptorItem]) -> Vec<Diagnostic> {
+  let mut offsets = Vec::with_capacity(items.len());
+  let mut data = vec![];
+  for item in items {
+    offsets.push(data.len());
+    let _ = item.try_write_into(&mut data);
+  };
+  validate_at(items, &offsets)
+}
+pub fn validate_bytes(data: &[u8]) -> Result<Vec<Diagnostic>> {
+  let mut items = vec![];
+  let mut offsets = vec![];
+  let mut offset = 0;
+  for item in encoded_items_from_bytes(data) {
+    let item = item?;
+    offsets.push(offset);
+    offset += item.original.as_ref().map_or(0, Vec::len);
+    items.push(item.item);
+  };
+  Ok(validate_at(&items, &offsets))
+}
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+  diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  const MOUSE: [u8; 45] = [
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x05, 0x09,
+    0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01,
+    0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01,
+    0x75, 0x05, 0x81, 0x03, 0x05, 0x01, 0x09, 0x30,
+    0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08,
+    0x95, 0x02, 0x81, 0x06, 0xC0,
+  ];
+  fn diagnostic(index: usize, offset: usize, kind: DiagnosticKind) -> Diagnostic {
+    Diagnostic { severity: kind.severity(), index, offset, kind }
+  }
+  #[test]
+  fn accepts_valid_descriptors() {
+    assert_eq!(validate_bytes(&MOUSE), Ok(vec![]));
+  }
+  #[test]
+  fn reports_rule_violations_with_offsets() {
+    let data = [
+      0x85, 0x00, 0x15, 0x05, 0x25, 0x01, 0x75, 0x03,
+      0x09, 0x30, 0x81, 0x02, 0x95, 0x01, 0xB4, 0xA9,
+      0x01, 0xA9, 0x01, 0xC0, 0xA1, 0x01, 0xA9, 0x00,
+      0x05, 0x01, 0x19, 0x01, 0x2B, 0x02, 0x00, 0x09,
+      0x00, 0x09, 0xFF, 0x25, 0x07, 0x91, 0x02,
+    ];
+    let diagnostics = validate_bytes(&data).unwrap();
+    assert_eq!(diagnostics, [
+      diagnostic(0, 0, DiagnosticKind::ReportIdZero),
+      diagnostic(4, 8, DiagnosticKind::MissingUsagePage),
+      diagnostic(5, 10, DiagnosticKind::MissingReportCount),
+      diagnostic(5, 10, DiagnosticKind::InvertedLogicalRange { minimum: 5, maximum: 1 }),
+      diagnostic(7, 14, DiagnosticKind::UnmatchedPop),
+      diagnostic(9, 17, DiagnosticKind::NestedDelimiter),
+      diagnostic(10, 19, DiagnosticKind::UnmatchedEndCollection),
+      diagnostic(9, 17, DiagnosticKind::UnclosedDelimiter),
+      diagnostic(12, 22, DiagnosticKind::UnmatchedDelimiter),
+      diagnostic(15, 28, DiagnosticKind::UsageRangeAcrossPages { minimum: 0x0001_0001, maximum: 0x0009_0002 }),
+      diagnostic(16, 33, DiagnosticKind::UnknownUsage { usage: 0x0001_00FF }),
+      diagnostic(11, 20, DiagnosticKind::UnclosedCollection),
+      diagnostic(18, 37, DiagnosticKind::UnalignedReport { ty: ReportType::Output, id: Some(0), bits: 3 }),
+    ]);
+    assert_eq!(diagnostics[10].severity, Severity::Warning);
+    assert!(has_errors(&diagnostics));
+    assert!(!has_errors(&diagnostics[10..11]));
+    assert_eq!(diagnostics[0].to_string(), "Error at item 0 (byte 0): Report ID 0 is reserved");
+    let items = items_from_bytes(&data).collect::<Result<Vec<_>>>().unwrap();
+    let revalidated = validate(&items);
+    assert!(revalidated.iter().map(|diagnostic| (diagnostic.index, &diagnostic.kind)).eq(diagnostics.iter().map(|diagnostic| (diagnostic.index, &diagnostic.kind))));
+    assert_eq!((revalidated[0].offset, revalidated[1].offset), (0, 7));
+  }
+  #[test]
+  fn reports_unnumbered_reports_and_missing_sizes() {
+    let data = [0x15, 0x00, 0x25, 0x01, 0x95, 0x08, 0x81, 0x02, 0x85, 0x01, 0x75, 0x01, 0x81, 0x02];
+    assert_eq!(validate_bytes(&data), Ok(vec![
+      diagnostic(3, 6, DiagnosticKind::MissingReportSize),
+      diagnostic(3, 6, DiagnosticKind::UnnumberedReport { ty: ReportType::Input }),
+    ]));
+  }
+}
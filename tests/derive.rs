@@ -0,0 +1,90 @@
+use hid::descriptors::*;
+use hid::error::Error;
+use hid::reports::*;
+#[derive(HidReport, Debug, PartialEq, Clone)]
+#[hid(input, id = 1)]
+struct Gamepad {
+  #[hid(usage_min = 0x0009_0001, usage_max = 0x0009_0004, size = 1)]
+  buttons: [bool; 4],
+  #[hid(constant, size = 4)]
+  padding: u8,
+  #[hid(usage = 0x0001_0039)]
+  hat: Option<u8>,
+  #[hid(usage = 0x0001_0030)]
+  x: Option<i8>,
+  #[hid(usage = 0x0001_0031)]
+  y: i16,
+  #[hid(usage = 0x0001_0032)]
+  pressed: Option<bool>,
+  #[hid(array, usage_min = 0x0007_0000, usage_max = 0x0007_0065, size = 8)]
+  keys: [Option<u32>; 2],
+}
+#[test]
+fn derive_round_trip() {
+  let report = Gamepad {
+    buttons: [true, false, false, true],
+    padding: 0,
+    hat: None,
+    x: None,
+    y: -300,
+    pressed: None,
+    keys: [Some(0x0007_0004), None],
+  };
+  let data = report.try_to_report().unwrap();
+  assert_eq!(Gamepad::try_from_report(data), Ok(report.clone()));
+  let report = Gamepad { hat: Some(254), x: Some(127), pressed: Some(true), ..report };
+  assert_eq!(Gamepad::from_report(report.to_report()), report);
+}
+#[test]
+fn option_fields_leave_room_for_null() {
+  let fields = Gamepad::report().fields;
+  let ranges: Vec<_> = fields.iter().map(|field| match *field {
+    ReportField::Variable { logical_minimum, logical_maximum, .. } | ReportField::Array { logical_minimum, logical_maximum, .. } => (logical_minimum, logical_maximum),
+  }).collect();
+  assert_eq!(ranges[5..], [(0, 254), (-127, 127), (-32768, 32767), (0, 1), (0, 101), (0, 101)]);
+  assert!(matches!(fields[8], ReportField::Variable { size: 2, .. }));
+}
+#[test]
+fn rejects_out_of_range_values() {
+  let report = Gamepad {
+    buttons: [false; 4],
+    padding: 0,
+    hat: Some(255),
+    x: None,
+    y: 0,
+    pressed: None,
+    keys: [None, None],
+  };
+  assert_eq!(report.try_to_report(), Err(Error::ValueOutOfRange { field: 5 }));
+}
+#[derive(HidReport, Debug, PartialEq, Clone)]
+#[hid(feature, id = 2)]
+struct Wide {
+  #[hid(usage = 0xFF00_0001)]
+  counter: u64,
+  #[hid(usage = 0xFF00_0002)]
+  offset: i64,
+  #[hid(usage = 0xFF00_0003, size = 40)]
+  delta: i64,
+  #[hid(usage = 0xFF00_0004, size = 8, buffered_bytes)]
+  serial: [u8; 4],
+}
+#[test]
+fn wide_fields_round_trip() {
+  let report = Wide { counter: u64::MAX, offset: i64::MIN, delta: -2, serial: [0x12, 0x34, 0x56, 0x78] };
+  let data = report.try_to_report().unwrap();
+  let mut expected = vec![0x02];
+  expected.extend([0xFF; 8]);
+  expected.extend(i64::MIN.to_le_bytes());
+  expected.extend([0xFE, 0xFF, 0xFF, 0xFF, 0xFF]);
+  expected.extend([0x12, 0x34, 0x56, 0x78]);
+  assert_eq!(data.as_raw_slice(), expected);
+  assert_eq!(Wide::try_from_report(data), Ok(report));
+  let values = read_report_from(&Wide::report(), &expected).unwrap();
+  assert_eq!(values[0], ReportFieldValue::Bytes(vec![0xFF; 8]));
+  assert_eq!(values[0].to_value::<u64>(0), Ok(u64::MAX));
+  assert_eq!(values[0].to_value::<i64>(0), Ok(-1));
+  assert_eq!(values[2].to_value::<i64>(2), Ok(-2));
+  assert_eq!(values[2].to_value::<u64>(2), Ok(0xFF_FFFF_FFFE));
+  assert_eq!(values[0].to_value::<u32>(0), Err(Error::ValueOutOfRange { field: 0 }));
+}